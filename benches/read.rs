@@ -0,0 +1,40 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use yagde::gd::char;
+
+fn fixtures() -> Vec<(String, Vec<u8>)> {
+    let mut saves: Vec<(String, Vec<u8>)> = fs::read_dir("./tests/save")
+        .unwrap()
+        .map(|e| e.unwrap().path().join("player.gdc"))
+        .filter(|p| p.exists())
+        .map(|p: PathBuf| {
+            let name = p.parent().unwrap().file_name().unwrap();
+            (name.to_string_lossy().into_owned(), fs::read(&p).unwrap())
+        })
+        .collect();
+    saves.sort();
+    saves
+}
+
+fn read_fixtures(c: &mut Criterion) {
+    let saves = fixtures();
+
+    for (name, data) in saves.iter() {
+        c.bench_function(&format!("read {name}"), |b| {
+            b.iter(|| char::Char::from_reader(Cursor::new(data)).unwrap())
+        });
+    }
+
+    c.bench_function("read all", |b| {
+        b.iter(|| {
+            for (_, data) in saves.iter() {
+                char::Char::from_reader(Cursor::new(data)).unwrap();
+            }
+        })
+    });
+}
+
+criterion_group!(benches, read_fixtures);
+criterion_main!(benches);
@@ -0,0 +1,14 @@
+//! Reads arbitrary bytes as a save. Parsing may fail, but must never panic
+//! or allocate beyond `Limits`; run with a memory cap to catch the latter:
+//!
+//!     cargo fuzz run read -- -malloc_limit_mb=64
+//!
+//! The saves in `tests/save` make a good seed corpus.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use yagde::gd::char::Char;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Char::from_reader(data);
+});
@@ -1,13 +1,29 @@
-use crate::gd::char;
+use yagde::backup::Backups;
+use yagde::config::{expand_home, Config, ConfigError};
+use yagde::discover::{game_root, mod_name, mod_roots, Discovery, Source};
+use yagde::gd::char;
+use yagde::gd::diff::Change;
+use yagde::gd::error::{Error as SaveError, ErrorKind};
+use yagde::gd::explain::explain;
+#[cfg(feature = "serde")]
+use yagde::gd::summary::HeaderSummary;
+use yagde::index::{CharEntry, Health, SaveIndex};
+use yagde::session::Session;
+use yagde::sync::{CloudSync, Pair, Side, State};
+#[cfg(feature = "tui")]
+use yagde::tui;
+#[cfg(feature = "serde")]
+use yagde::util::write_atomic;
 
-use std::collections::HashMap;
+use std::fs;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
-use std::{env, fs, io};
+use std::process::ExitCode;
 
-use anyhow::{bail, Ok, Result};
-use clap::Parser;
+use anyhow::{bail, Context, Ok, Result};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use copy_dir::copy_dir;
-use inquire::{Select, Text};
+use inquire::{Confirm, Select, Text};
 use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter};
 use tracing::{debug, span, Level};
@@ -16,9 +32,273 @@ use tracing::{debug, span, Level};
 #[command(name = "yagde")]
 #[command(author = "wr8fdy")]
 #[command(about = "Grim Dawn save file editor", version = None, long_about = None)]
+#[command(after_help = EXIT_CODES)]
 struct Cli {
-    #[arg(short = 's', long)]
+    #[arg(short = 's', long, global = true)]
     save_path: Option<String>,
+
+    /// Change saves without asking first
+    #[arg(short = 'y', long, global = true)]
+    yes: bool,
+
+    /// Show what a command would change without writing anything
+    #[arg(short = 'n', long, global = true)]
+    dry_run: bool,
+
+    /// Backups kept per character, 0 turns them off [default: 10, or as configured]
+    #[arg(long, global = true)]
+    keep_backups: Option<usize>,
+
+    #[arg(skip)]
+    config: Config,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  any other error
+  2  invalid arguments
+  3  character, save folder or backup not found, or ambiguous
+  4  a save file could not be parsed
+  5  a change was not confirmed
+  6  the character is protected in the config";
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the characters of the save folder
+    List {
+        /// [default: text, or as configured]
+        #[arg(long, value_enum)]
+        output: Option<Output>,
+    },
+    /// Show the main stats of a character
+    View {
+        #[command(flatten)]
+        target: Target,
+        /// [default: text, or as configured]
+        #[arg(long, value_enum)]
+        output: Option<Output>,
+    },
+    /// Change the name of a character
+    Rename {
+        #[command(flatten)]
+        target: Target,
+        name: String,
+    },
+    /// Copy a character under a new name, or to another save folder
+    Clone {
+        #[command(flatten)]
+        target: Target,
+        /// Name of the copy, the same name when copying to another folder
+        #[arg(required_unless_present = "to")]
+        name: Option<String>,
+        /// Copy to `main` or to the folder of a mod instead
+        #[arg(long, value_name = "FOLDER")]
+        to: Option<String>,
+    },
+    /// Reset parts of the progress of a character
+    Reset(ResetArgs),
+    /// Give a character more iron
+    AddMoney {
+        #[command(flatten)]
+        target: Target,
+        amount: u32,
+    },
+    /// Max out the reputation with friendly or hostile factions
+    BoostFactions {
+        #[command(flatten)]
+        target: Target,
+        #[arg(value_enum)]
+        side: FactionSide,
+    },
+    /// Print every field of a save file with its offset, raw bytes and value
+    Explain { file: PathBuf },
+    /// Convert a character to the newest save layout
+    Migrate {
+        file: PathBuf,
+        /// Use the layout of this save instead, e.g. one made by another game install
+        #[arg(long)]
+        like: Option<PathBuf>,
+    },
+    /// Show what changed between two saves, e.g. a backup and the current one
+    Diff {
+        /// Older save: a file, a save directory or a character name
+        old: String,
+        /// Newer save: a file, a save directory or a character name
+        new: String,
+    },
+    /// Copy the newer of the local and Steam Cloud saves of each character
+    /// over the older one
+    Sync {
+        /// Local save folder, found on this machine by default
+        #[arg(long)]
+        local: Option<PathBuf>,
+        /// Steam Cloud save folder, found on this machine by default
+        #[arg(long)]
+        cloud: Option<PathBuf>,
+        /// Copy to keep of characters whose copies differ but look equally
+        /// new, which are left alone otherwise
+        #[arg(long, value_enum)]
+        prefer: Option<SyncSide>,
+    },
+    /// Browse and edit a character in a full-screen terminal UI
+    #[cfg(feature = "tui")]
+    Tui {
+        #[command(flatten)]
+        target: Target,
+    },
+    /// Manage the backups taken before every change
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+    /// Show or change the settings of the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Write a character as text, to keep it under version control
+    #[cfg(feature = "serde")]
+    Export {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t)]
+        format: TextFormat,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Turn an exported character back into a save file
+    #[cfg(feature = "serde")]
+    Import {
+        input: PathBuf,
+        file: PathBuf,
+        /// Format of the input, guessed from its extension by default
+        #[arg(long, value_enum)]
+        format: Option<TextFormat>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BackupCommand {
+    /// List the backups of every character
+    List,
+    /// Put a character back the way it was in a backup
+    Restore {
+        /// As shown by `backup list`, e.g. `_Bob/20240102-030405`
+        id: String,
+    },
+    /// Remove the oldest backups, past the --keep-backups count
+    Prune,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print a setting, or all of them
+    Get { key: Option<String> },
+    /// Change a setting, an empty value unsets it, lists are comma separated
+    Set { key: String, value: String },
+}
+
+#[derive(Args, Debug)]
+struct Target {
+    /// Name of the character, or its save directory
+    character: String,
+}
+
+#[derive(Args, Debug)]
+#[command(group(
+    ArgGroup::new("parts")
+        .required(true)
+        .multiple(true)
+        .args(["deaths", "skills", "attributes", "devotions", "all"]),
+))]
+struct ResetArgs {
+    #[command(flatten)]
+    target: Target,
+    #[arg(long)]
+    deaths: bool,
+    #[arg(long)]
+    skills: bool,
+    #[arg(long)]
+    attributes: bool,
+    #[arg(long)]
+    devotions: bool,
+    #[arg(long)]
+    all: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Output {
+    /// Aligned text for people
+    #[default]
+    Text,
+    /// JSON for scripts
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum FactionSide {
+    Friendly,
+    Hostile,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SyncSide {
+    Local,
+    Cloud,
+}
+
+impl From<SyncSide> for Side {
+    fn from(side: SyncSide) -> Self {
+        match side {
+            SyncSide::Local => Side::Local,
+            SyncSide::Cloud => Side::Cloud,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum TextFormat {
+    #[default]
+    Json,
+    Toml,
+}
+
+/// A character of `yagde list --output json`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct ListEntry<'a> {
+    dir: &'a Path,
+    health: String,
+    #[serde(rename = "mod", skip_serializing_if = "Option::is_none")]
+    mod_name: &'a Option<String>,
+    #[serde(flatten)]
+    header: HeaderSummary,
+}
+
+/// Errors of the command line itself, each with its own exit code.
+#[derive(thiserror::Error, Debug)]
+enum CliError {
+    #[error("no character named {0} or stored in it")]
+    NotFound(String),
+    #[error("several characters are named {0}, pass one of their directories instead: {1}")]
+    Ambiguous(String, String),
+    #[error("several save folders found, pick one with --save-path: {0}")]
+    SeveralRoots(String),
+    #[error("{0} needs a confirmation, pass --yes when running without a terminal")]
+    NotConfirmed(String),
+    #[error("cancelled")]
+    Cancelled,
+    #[error("no backup {0}, see `yagde backup list`")]
+    NoBackup(String),
+    #[error("{0} is protected in the config, it's never written")]
+    Protected(String),
+    #[error("pick the {side} save folder with --{side}, found: {found}")]
+    SyncRoot { side: Side, found: String },
 }
 
 #[derive(Display, EnumIter, PartialEq, Eq)]
@@ -30,6 +310,22 @@ enum CharOpt {
     AddMoney,
     BoostFrendlyFactions,
     BoostHostileFactions,
+    Undo,
+    Redo,
+    Save,
+    Discard,
+    #[strum(serialize = "\u{23CE} Return")]
+    Return,
+    #[strum(serialize = "\u{274C} Exit")]
+    Exit,
+}
+
+#[derive(Display, EnumIter)]
+enum BrokenOpt {
+    #[strum(serialize = "Inspect")]
+    Explain,
+    #[strum(serialize = "Restore a backup")]
+    Restore,
     #[strum(serialize = "\u{23CE} Return")]
     Return,
     #[strum(serialize = "\u{274C} Exit")]
@@ -54,89 +350,530 @@ pub fn run() -> Result<()> {
     let span = span!(Level::INFO, "app");
     let _enter = span.enter();
 
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    if let Some(path) = Config::path() {
+        cli.config =
+            Config::load(&path).with_context(|| format!("failed to load {}", path.display()))?;
+    }
 
-    let path: PathBuf;
-    if let Some(p) = &cli.save_path {
-        path = Path::new(&p.replace("$HOME", &env::var("HOME")?)).to_path_buf();
-    } else {
-        path = find_save_files()?;
+    match &cli.command {
+        Some(command) => run_command(&cli, command),
+        None => run_interactive(&cli),
     }
+}
+
+/// Exit code for an error returned by `run`, see `EXIT_CODES`.
+pub fn exit_code(e: &anyhow::Error) -> ExitCode {
+    if let Some(e) = e.downcast_ref::<CliError>() {
+        return match e {
+            CliError::NotFound(_)
+            | CliError::Ambiguous(..)
+            | CliError::SeveralRoots(_)
+            | CliError::NoBackup(_)
+            | CliError::SyncRoot { .. } => 3,
+            CliError::NotConfirmed(_) | CliError::Cancelled => 5,
+            CliError::Protected(_) => 6,
+        }
+        .into();
+    }
+    if let Some(ConfigError::UnknownKey(_) | ConfigError::InvalidValue { .. }) =
+        e.downcast_ref::<ConfigError>()
+    {
+        return 2.into();
+    }
+    match e.downcast_ref::<SaveError>() {
+        Some(e) if !matches!(e.kind, ErrorKind::Io(_)) => 4.into(),
+        _ => ExitCode::FAILURE,
+    }
+}
+
+fn run_command(cli: &Cli, command: &Command) -> Result<()> {
+    match command {
+        Command::List { output } => {
+            let chars = SaveIndex::new(&save_root(cli, false)?)
+                .with_mods(true)
+                .scan()?;
+            match output_format(cli, *output) {
+                Output::Text => {
+                    for c in chars.iter() {
+                        println!("{0: <50} {1}", c.label(), c.dir.display());
+                    }
+                }
+                #[cfg(feature = "serde")]
+                Output::Json => {
+                    let entries: Vec<ListEntry> = chars
+                        .iter()
+                        .map(|c| ListEntry {
+                            dir: &c.dir,
+                            health: c.health.to_string(),
+                            mod_name: &c.mod_name,
+                            header: (&c.header).into(),
+                        })
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&entries)?);
+                }
+            }
+        }
+        Command::View { target, output } => {
+            let (_, c) = load(cli, target)?;
+            match output_format(cli, *output) {
+                Output::Text => c.print_info(),
+                #[cfg(feature = "serde")]
+                Output::Json => println!("{}", serde_json::to_string_pretty(&c.summary())?),
+            }
+        }
+        Command::Rename { target, name } => {
+            let (file, mut c) = load(cli, target)?;
+            let old_name = c.header.name.clone();
+            if apply(
+                cli,
+                &mut c,
+                &file,
+                &format!("Rename {old_name} to {name}"),
+                |c| {
+                    c.rename(name);
+                },
+            )? {
+                println!("Successfully renamed from {old_name} to {name}");
+            }
+        }
+        Command::Clone { target, name, to } => {
+            let (file, c) = load(cli, target)?;
+            let dir = file.parent().unwrap_or(Path::new("."));
+            let root = clone_root(dir, to.as_deref())?;
+            let name = name.as_ref().unwrap_or(&c.header.name);
+            check_protected(cli, &root.join(format!("_{name}")), name)?;
+            if !dry_run(cli) {
+                clone_char(&root, dir, name)?;
+                println!("Successfully cloned {} to {name}", c.header.name);
+            }
+        }
+        Command::Reset(args) => {
+            let (file, mut c) = load(cli, &args.target)?;
+            let parts: Vec<&str> = [
+                (args.deaths, "deaths"),
+                (args.skills, "skills"),
+                (args.attributes, "attributes"),
+                (args.devotions, "devotions"),
+                (args.all, "everything"),
+            ]
+            .into_iter()
+            .filter_map(|(on, part)| on.then_some(part))
+            .collect();
+            let action = format!("Reset {} of {}", parts.join(", "), c.header.name);
+            let written = apply(cli, &mut c, &file, &action, |c| {
+                if args.all {
+                    c.reset_all();
+                }
+                if args.deaths {
+                    c.reset_deaths();
+                }
+                if args.skills {
+                    c.reset_skills();
+                }
+                if args.attributes {
+                    c.reset_attributes();
+                }
+                if args.devotions {
+                    c.reset_devotions();
+                }
+            })?;
+            if written {
+                println!("Reset {} done", parts.join(", "));
+            }
+        }
+        Command::AddMoney { target, amount } => {
+            let (file, mut c) = load(cli, target)?;
+            let action = format!("Add {amount} iron to {}", c.header.name);
+            if apply(cli, &mut c, &file, &action, |c| {
+                c.add_money(*amount);
+            })? {
+                println!("Current balance is {}", c.info.money);
+            }
+        }
+        Command::BoostFactions { target, side } => {
+            let (file, mut c) = load(cli, target)?;
+            let action = format!("Boost factions of {}", c.header.name);
+            let written = apply(cli, &mut c, &file, &action, |c| {
+                match side {
+                    FactionSide::Friendly => c.boost_frendly_factions(),
+                    FactionSide::Hostile => c.boost_hostile_factions(),
+                };
+            })?;
+            if !written {
+                return Ok(());
+            }
+            match side {
+                FactionSide::Friendly => println!("Frendly factions are boosted!"),
+                FactionSide::Hostile => println!("Hostile factions are boosted!"),
+            }
+        }
+        Command::Explain { file } => {
+            let explanation = explain(&fs::read(file)?);
+            print!("{explanation}");
+            if let Some(e) = explanation.error {
+                return Err(e).context(format!("failed to parse {}", file.display()));
+            }
+        }
+        Command::Migrate { file, like } => {
+            let to = match like {
+                Some(l) => char::Char::new().read(l)?.format(),
+                None => char::Format::LATEST,
+            };
+            let mut c = char::Char::new();
+            c.read(file)?;
+            for field in c.migrate(&to)? {
+                println!("{field} will be dropped, the new layout has no room for it");
+            }
+            if proceed(cli, &format!("Migrate {}", file.display()))? {
+                save(cli, &mut c, file)?;
+            }
+        }
+        Command::Diff { old, new } => {
+            let (_, a) = load(
+                cli,
+                &Target {
+                    character: old.clone(),
+                },
+            )?;
+            let (_, b) = load(
+                cli,
+                &Target {
+                    character: new.clone(),
+                },
+            )?;
+            let changes = a.diff(&b);
+            if changes.is_empty() {
+                println!("No changes");
+            }
+            for change in changes {
+                println!("{change}");
+            }
+        }
+        Command::Sync {
+            local,
+            cloud,
+            prefer,
+        } => {
+            let sync = CloudSync::new(
+                &sync_root(cli, Side::Local, local)?,
+                &sync_root(cli, Side::Cloud, cloud)?,
+            );
+            let pairs = sync.pairs()?;
+            for pair in pairs.iter() {
+                println!("{} ({})", pair.name(), pair.uid);
+                for side in [Side::Local, Side::Cloud] {
+                    match pair.get(side) {
+                        Some(c) => println!("  {side}: {c}"),
+                        None => println!("  {side}: missing"),
+                    }
+                }
+                match (pair.state, prefer) {
+                    (State::Conflict, Some(side)) => {
+                        println!("  {}, keeping {}", pair.state, Side::from(*side))
+                    }
+                    (state, _) => println!("  {state}"),
+                }
+            }
+
+            let conflicts = pairs.iter().filter(|p| p.state == State::Conflict).count();
+            let outdated: Vec<(&Pair, Side)> = pairs
+                .iter()
+                .filter_map(|p| match (p.state, prefer) {
+                    (State::Newer(side), _) => Some((p, side)),
+                    (State::Conflict, Some(side)) => Some((p, Side::from(*side))),
+                    _ => None,
+                })
+                .collect();
+            if conflicts > 0 && prefer.is_none() {
+                println!(
+                    "{conflicts} characters conflict and are left alone, pick the copy to keep with --prefer local or --prefer cloud"
+                );
+            }
+            if outdated.is_empty() {
+                if conflicts == 0 {
+                    println!("Everything is in sync");
+                }
+                return Ok(());
+            }
+            if proceed(cli, &format!("Sync {} characters", outdated.len()))? {
+                for (pair, side) in outdated {
+                    sync.apply(pair, side, |file| before_write(cli, file))?;
+                }
+                println!("Synced");
+            }
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui { target } => {
+            let mut app = tui::App::new(Session::open(&resolve(cli, target)?)?);
+            let mut terminal = ratatui::init();
+            let result = tui::run(&mut terminal, &mut app, |file| {
+                if cli.dry_run {
+                    return Err(io::Error::other("dry run, nothing written"));
+                }
+                before_write(cli, file).map_err(io::Error::other)
+            });
+            ratatui::restore();
+            result?;
+        }
+        Command::Backup { command } => {
+            let backups = backups(cli, &save_root(cli, false)?);
+            match command {
+                BackupCommand::List => {
+                    for b in backups.list()? {
+                        println!("{}", b.id);
+                    }
+                }
+                BackupCommand::Restore { id } => {
+                    let Some(b) = backups.find(id)? else {
+                        return Err(CliError::NoBackup(id.clone()).into());
+                    };
+                    let dir = save_root(cli, false)?.join(&b.character);
+                    for file in [dir.join("player.gdc"), b.dir.join("player.gdc")] {
+                        check_protected(cli, &dir, &name_of(&file))?;
+                    }
+                    if proceed(cli, &format!("Restore {} from {id}", b.character))? {
+                        backups.restore(&b)?;
+                        println!("Restored {id}");
+                    }
+                }
+                BackupCommand::Prune => {
+                    let old = backups.prunable()?;
+                    for b in old.iter() {
+                        println!("{} will be removed", b.id);
+                    }
+                    if !old.is_empty() && proceed(cli, "Remove old backups")? {
+                        let removed = backups.prune()?;
+                        println!("Removed {} backups", removed.len());
+                    }
+                }
+            }
+        }
+        Command::Config { command } => match command {
+            ConfigCommand::Get { key: Some(key) } => println!("{}", cli.config.get(key)?),
+            ConfigCommand::Get { key: None } => {
+                for key in Config::KEYS {
+                    println!("{key} = {}", cli.config.get(key)?);
+                }
+            }
+            ConfigCommand::Set { key, value } => {
+                let path = Config::path().ok_or(ConfigError::NoPath)?;
+                let mut config = cli.config.clone();
+                config.set(key, value)?;
+                config.save(&path)?;
+                println!("{key} = {}", config.get(key)?);
+            }
+        },
+        #[cfg(feature = "serde")]
+        Command::Export {
+            file,
+            format,
+            output,
+        } => {
+            let mut c = char::Char::new();
+            c.read(file)?;
+            let text = match format {
+                TextFormat::Json => serde_json::to_string_pretty(&c)? + "\n",
+                TextFormat::Toml => toml::to_string(&c)?,
+            };
+            match output {
+                Some(o) => write_atomic(o, text.as_bytes())?,
+                None => print!("{text}"),
+            }
+        }
+        #[cfg(feature = "serde")]
+        Command::Import {
+            input,
+            file,
+            format,
+        } => {
+            let text = fs::read_to_string(input)?;
+            let format = format.unwrap_or(match input.extension() {
+                Some(e) if e == "toml" => TextFormat::Toml,
+                _ => TextFormat::Json,
+            });
+            let mut c: char::Char = match format {
+                TextFormat::Json => serde_json::from_str(&text)?,
+                TextFormat::Toml => toml::from_str(&text)?,
+            };
+            if file.exists() {
+                let mut old = char::Char::new();
+                old.with_tolerant(true).read(file)?;
+                println!("Overwrite {}:", file.display());
+                for change in old.diff(&c) {
+                    println!("  {change}");
+                }
+            }
+            if proceed(cli, &format!("Write {}", file.display()))? {
+                save(cli, &mut c, file)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_interactive(cli: &Cli) -> Result<()> {
+    let path = save_root(cli, true)?;
+    let mut index = SaveIndex::new(&path);
+    index.with_mods(true);
 
     'char_select: loop {
         // reload chars after potential clone
-        let chars = get_chars_names(&path)?;
-        let mut options: Vec<&String> = chars.keys().collect();
-        let exit = "\u{274C} Exit".to_owned();
-        options.sort();
-        options.push(&exit);
+        let chars = index.scan()?;
+        let mut options: Vec<String> = chars.iter().map(|c| c.label()).collect();
+        options.push("\u{274C} Exit".to_owned());
 
-        let char = Select::new("Choose character:", options.clone())
+        let choice = Select::new("Choose character:", options)
             .with_page_size(15)
-            .prompt()?;
+            .raw_prompt()?;
 
-        if char == &exit {
+        let Some(entry) = chars.get(choice.index) else {
             break;
-        }
+        };
 
-        let mut current_char = char::Char::new();
-        let current_char_dir = &chars[char];
-        let file_path = &current_char_dir.join("player.gdc");
-        current_char.read(file_path)?;
+        let current_char_dir = &entry.dir;
+        if !entry.is_ok() && !broken_menu(cli, entry)? {
+            break;
+        }
+        if !entry.is_ok() {
+            continue;
+        }
+        let mut session = match Session::open(&entry.file()) {
+            Result::Ok(s) => s,
+            Err(e) => {
+                println!("{} can't be opened: {e}", entry.header.name);
+                let entry = CharEntry {
+                    health: Health::from(&e),
+                    ..entry.clone()
+                };
+                if !broken_menu(cli, &entry)? {
+                    break;
+                }
+                continue;
+            }
+        };
 
         loop {
-            let action = Select::new("Choose action:", CharOpt::iter().collect())
+            let prompt = match session.is_dirty() {
+                true => "Choose action (unsaved changes):",
+                false => "Choose action:",
+            };
+            let action = Select::new(prompt, CharOpt::iter().collect())
                 .with_page_size(15)
                 .prompt()?;
 
             match action {
-                CharOpt::View => current_char.print_info(),
+                CharOpt::View => session.char().print_info(),
                 CharOpt::Rename => {
-                    let old_name = current_char.header.name.clone();
+                    let old_name = session.char().header.name.clone();
                     let new_name = Text::new("Enter a new name:").prompt()?;
-                    current_char.rename(&new_name).save_as(file_path)?;
-                    println!("Successfully renamed from {} to {}", old_name, &new_name);
-                }
-                CharOpt::Clone => {
-                    let new_name = clone_char(&path, current_char_dir)?;
-                    println!(
-                        "Successfully cloned {} to {}",
-                        current_char.header.name, new_name
+                    stage(
+                        &mut session,
+                        &format!("Rename {old_name} to {new_name}"),
+                        |c| {
+                            c.rename(&new_name);
+                        },
                     );
                 }
-                CharOpt::AddMoney => {
-                    current_char.info.money = current_char.info.money.saturating_add(10_000_000);
-                    current_char.save_as(file_path)?;
-                    println!("Current balance is {}", current_char.info.money);
+                CharOpt::Clone => {
+                    let root = choose_clone_root(current_char_dir)?;
+                    let same_root = Some(root.as_path()) == current_char_dir.parent();
+                    let old_name = &session.char().header.name;
+                    let new_name = match same_root {
+                        true => Text::new("Enter a new name:").prompt()?,
+                        false => Text::new("Enter a new name:")
+                            .with_default(old_name)
+                            .prompt()?,
+                    };
+                    check_protected(cli, &root.join(format!("_{new_name}")), &new_name)?;
+                    if session.is_dirty() {
+                        println!("Cloning the saved character, without the unsaved changes");
+                    }
+                    if !dry_run(cli) {
+                        clone_char(&root, current_char_dir, &new_name)?;
+                        println!(
+                            "Successfully cloned {} to {}",
+                            session.char().header.name,
+                            new_name
+                        );
+                    }
                 }
+                CharOpt::AddMoney => stage(&mut session, "Add 10000000 iron", |c| {
+                    c.add_money(10_000_000);
+                }),
                 CharOpt::BoostFrendlyFactions => {
-                    current_char.boost_frendly_factions().save_as(file_path)?;
-                    println!("Frendly factions are boosted!");
+                    stage(&mut session, "Boost frendly factions", |c| {
+                        c.boost_frendly_factions();
+                    })
                 }
                 CharOpt::BoostHostileFactions => {
-                    current_char.boost_hostile_factions().save_as(file_path)?;
-                    println!("Hostile factions are boosted!");
+                    stage(&mut session, "Boost hostile factions", |c| {
+                        c.boost_hostile_factions();
+                    })
                 }
                 CharOpt::Reset => loop {
                     let reset_action = Select::new("Choose action:", ResetOpt::iter().collect())
                         .with_page_size(15)
                         .prompt()?;
 
-                    match reset_action {
-                        ResetOpt::All => current_char.reset_all().save_as(file_path)?,
-                        ResetOpt::Skills => current_char.reset_skills().save_as(file_path)?,
-                        ResetOpt::Attributes => {
-                            current_char.reset_attributes().save_as(file_path)?
-                        }
-                        ResetOpt::Devotions => current_char.reset_devotions().save_as(file_path)?,
-                        ResetOpt::Deaths => current_char.reset_deaths().save_as(file_path)?,
+                    let edit: fn(&mut char::Char) = match reset_action {
+                        ResetOpt::All => |c| {
+                            c.reset_all();
+                        },
+                        ResetOpt::Skills => |c| {
+                            c.reset_skills();
+                        },
+                        ResetOpt::Attributes => |c| {
+                            c.reset_attributes();
+                        },
+                        ResetOpt::Devotions => |c| {
+                            c.reset_devotions();
+                        },
+                        ResetOpt::Deaths => |c| {
+                            c.reset_deaths();
+                        },
                         ResetOpt::Return => break,
-                        ResetOpt::Exit => break 'char_select,
+                        ResetOpt::Exit if leave(&session)? => break 'char_select,
+                        ResetOpt::Exit => break,
+                    };
+                    stage(&mut session, &format!("Reset {reset_action}"), edit);
+                },
+                CharOpt::Undo => match session.undo() {
+                    true => println!("Undone"),
+                    false => println!("Nothing to undo"),
+                },
+                CharOpt::Redo => match session.redo() {
+                    true => println!("Redone"),
+                    false => println!("Nothing to redo"),
+                },
+                CharOpt::Save => {
+                    let action = format!("Save {}", session.char().header.name);
+                    if !session.is_dirty() {
+                        println!("{action}: nothing to change");
+                        continue;
+                    }
+                    show(&action, &session.pending());
+                    if in_menu(proceed(cli, &action))? {
+                        before_write(cli, session.file())?;
+                        session.save()?;
+                        println!("Saved");
                     }
-                    println!("Reset {reset_action} done");
+                }
+                CharOpt::Discard => match session.discard() {
+                    true => println!("Unsaved changes discarded, undo brings them back"),
+                    false => println!("Nothing to discard"),
                 },
-                CharOpt::Return => break,
-                CharOpt::Exit => break 'char_select,
+                CharOpt::Return => {
+                    if leave(&session)? {
+                        break;
+                    }
+                }
+                CharOpt::Exit => {
+                    if leave(&session)? {
+                        break 'char_select;
+                    }
+                }
             }
         }
     }
@@ -144,110 +881,413 @@ pub fn run() -> Result<()> {
     Ok(())
 }
 
-fn get_chars_names(p: &PathBuf) -> Result<HashMap<String, PathBuf>> {
-    let char_dirs = fs::read_dir(p)?
-        .map(|res| res.map(|e| e.path()))
-        .collect::<Result<Vec<_>, io::Error>>()?;
+/// Save folder from `--save-path`, the config, or the one found on this
+/// machine.
+fn save_root(cli: &Cli, interactive: bool) -> Result<PathBuf> {
+    if let Some(p) = &cli.save_path {
+        return Ok(expand_home(p));
+    }
+    let roots = cli.config.save_roots();
+    if roots.is_empty() {
+        return choose_root(cli, find_save_files()?, interactive);
+    }
+    let options = roots
+        .into_iter()
+        .map(|r| (r.display().to_string(), r))
+        .collect();
+    choose_root(cli, options, interactive)
+}
 
-    let mut chars = HashMap::new();
-    for char_dir in char_dirs.iter() {
-        if char_dir.to_str().unwrap().contains("__") {
-            continue;
-        }
-        let mut c = char::Char::new();
-        let char_file = char_dir.join("player.gdc");
-        chars.insert(c.get_name(&char_file)?, char_dir.clone());
+/// Local or Steam Cloud save folder of `sync`, the only one of the main game
+/// found unless given. Cloud folders of other Steam accounts than the
+/// configured one are left out.
+fn sync_root(cli: &Cli, side: Side, given: &Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(p) = given {
+        return Ok(expand_home(&p.to_string_lossy()));
+    }
+    let mut found: Vec<PathBuf> = Discovery::from_env()
+        .map(|d| d.roots())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|r| r.mod_name.is_none())
+        .filter(|r| match (&r.source, side) {
+            (Source::Steam { .. }, Side::Local) => true,
+            (Source::SteamCloud { account }, Side::Cloud) => cli
+                .config
+                .steam_account
+                .as_ref()
+                .is_none_or(|a| a == account),
+            _ => false,
+        })
+        .map(|r| r.path)
+        .collect();
+    if found.len() == 1 {
+        return Ok(found.remove(0));
     }
+    let found = match found.is_empty() {
+        true => "none".to_owned(),
+        false => found
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    Err(CliError::SyncRoot { side, found }.into())
+}
 
-    Ok(chars)
+/// `text` unless the config or `--output` says otherwise.
+fn output_format(cli: &Cli, output: Option<Output>) -> Output {
+    output
+        .or_else(|| {
+            let configured = cli.config.output.as_deref()?;
+            Output::from_str(configured, true).ok()
+        })
+        .unwrap_or_default()
 }
 
-fn find_save_files() -> Result<PathBuf> {
-    let home_dir = &env::var("HOME")?;
+/// Finds the `player.gdc` of a character given by name or by directory.
+/// Directories can be relative to the save folder, e.g. `_Bob`.
+fn resolve(cli: &Cli, target: &Target) -> Result<PathBuf> {
+    let arg = Path::new(&target.character);
+    if arg.is_file() {
+        return Ok(arg.to_path_buf());
+    }
+    if arg.join("player.gdc").is_file() {
+        return Ok(arg.join("player.gdc"));
+    }
 
-    let steam_paths = [
-        ".local/share/Steam",
-        ".steam/debian-installation",
-        ".var/app/com.valvesoftware.Steam/data/Steam",
-    ];
+    let root = save_root(cli, false)?;
+    if root.join(arg).join("player.gdc").is_file() {
+        return Ok(root.join(arg).join("player.gdc"));
+    }
 
-    let mut steam_path = PathBuf::new();
-    for p in steam_paths.iter() {
-        steam_path.push(home_dir);
-        steam_path.push(p);
-        if steam_path.exists() {
-            debug!(?steam_path, "found steam in");
-            break;
-        }
-        steam_path.clear();
+    let chars = SaveIndex::new(&root).with_mods(true).scan()?;
+    let found: Vec<_> = chars
+        .iter()
+        .filter(|c| c.header.name == target.character)
+        .collect();
+    match found[..] {
+        [] => Err(CliError::NotFound(target.character.clone()).into()),
+        [c] => Ok(c.file()),
+        _ => Err(CliError::Ambiguous(
+            target.character.clone(),
+            found
+                .iter()
+                .map(|c| c.dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+        .into()),
     }
+}
 
-    let mut path_options: HashMap<String, PathBuf> = HashMap::new();
-    add_local_files(&mut path_options, &steam_path)?;
-    add_remote_files(&mut path_options, &steam_path)?;
+fn backups(cli: &Cli, root: &Path) -> Backups {
+    let keep = cli
+        .keep_backups
+        .or(cli.config.keep_backups)
+        .unwrap_or(Backups::DEFAULT_KEEP);
+    let mut b = Backups::new(root);
+    b.with_keep(keep);
+    b
+}
 
-    if path_options.is_empty() {
-        bail!("could not detect save files location")
+/// Backs up the character directory of `file`, then writes `c` over it.
+fn save(cli: &Cli, c: &mut char::Char, file: &PathBuf) -> Result<()> {
+    before_write(cli, file)?;
+    c.save_as(file)?;
+    Ok(())
+}
+
+/// Refuses to write a protected character, then backs up the character
+/// directory of `file`. Loose save files outside of a character directory
+/// aren't backed up.
+fn before_write(cli: &Cli, file: &Path) -> Result<()> {
+    if file.exists() {
+        check_protected(cli, file.parent().unwrap_or(Path::new("")), &name_of(file))?;
+    }
+    if file.ends_with("player.gdc") && file.exists() {
+        let file = file.canonicalize()?;
+        let dir = file.parent().unwrap_or(Path::new("/"));
+        let root = dir.parent().unwrap_or(Path::new("/"));
+        if let Some(b) = backups(cli, root).create(dir)? {
+            debug!(id = b.id, "backed up");
+        }
     }
+    Ok(())
+}
 
-    let save_files_path =
-        Select::new("Choose save files location:", path_options.keys().collect()).prompt()?;
+fn check_protected(cli: &Cli, dir: &Path, name: &str) -> Result<()> {
+    let dir = dir.canonicalize().unwrap_or(dir.to_path_buf());
+    if cli.config.is_protected(&dir, name) {
+        return Err(CliError::Protected(name.to_owned()).into());
+    }
+    Ok(())
+}
 
-    Ok(path_options[save_files_path].clone())
+/// Name of the character saved in `file`, empty if it can't be read.
+fn name_of(file: &Path) -> String {
+    char::Char::read_header(file)
+        .map(|h| h.name)
+        .unwrap_or_default()
 }
 
-fn add_local_files(
-    path_options: &mut HashMap<String, PathBuf>,
-    steam_path: &PathBuf,
-) -> Result<()> {
-    const STEAM_LOCAL_PATH: &str = r"steamapps/compatdata/219990/pfx/drive_c/users/steamuser/Documents/My Games/Grim Dawn/save/main";
+fn load(cli: &Cli, target: &Target) -> Result<(PathBuf, char::Char)> {
+    let file = resolve(cli, target)?;
+    let mut c = char::Char::new();
+    c.with_tolerant(true)
+        .read(&file)
+        .with_context(|| format!("failed to read {}", file.display()))?;
+    Ok((file, c))
+}
 
-    let mut path = PathBuf::new();
+/// Applies `edit` to a copy of `c`, shows what it changes and writes it
+/// once confirmed. Returns whether the save was written.
+fn apply(
+    cli: &Cli,
+    c: &mut char::Char,
+    file: &PathBuf,
+    action: &str,
+    edit: impl FnOnce(&mut char::Char),
+) -> Result<bool> {
+    let mut edited = c.clone();
+    edit(&mut edited);
 
-    path.push(steam_path);
-    path.push(STEAM_LOCAL_PATH);
-    if path.exists() {
-        let count = path.read_dir()?.count();
-        path_options.insert(format!("local ({count} chars)"), path.clone());
+    // the diff is only the preview, whether to write is up to the whole save
+    show(action, &c.diff(&edited));
+    if edited == *c {
+        return Ok(false);
     }
 
-    Ok(())
+    if !proceed(cli, action)? {
+        return Ok(false);
+    }
+    save(cli, &mut edited, file)?;
+    *c = edited;
+    Ok(true)
 }
 
-fn add_remote_files(path_options: &mut HashMap<String, PathBuf>, steam_path: &Path) -> Result<()> {
-    const STEAM_REMOTE_ACCOUNT_PATH: &str = r"userdata";
-    const STEAM_REMOTE_PATH: &str = r"219990/remote/save/main";
+/// Applies `edit` to the session and shows what it changed. Nothing is
+/// written until the session is saved.
+fn stage(session: &mut Session, action: &str, edit: impl FnOnce(&mut char::Char)) {
+    match session.edit(edit) {
+        Result::Ok(changes) => show(action, &changes.unwrap_or_default()),
+        Err(e) => println!("{action}: {e}"),
+    }
+}
 
-    let mut path = PathBuf::new();
+fn show(action: &str, changes: &[Change]) {
+    if changes.is_empty() {
+        println!("{action}: nothing to change");
+        return;
+    }
+    println!("{action}:");
+    for change in changes {
+        println!("  {change}");
+    }
+}
 
-    for p in steam_path.join(STEAM_REMOTE_ACCOUNT_PATH).read_dir()? {
-        let p = p?;
-        path.push(p.path().clone());
-        path.push(STEAM_REMOTE_PATH);
-        if path.exists() {
-            let count = path.read_dir()?.count();
-            path_options.insert(
-                format!("remote account {:?} ({count} chars)", p.file_name().clone()),
-                path.clone(),
-            );
+/// Menu of a save that can't be read, returns false to exit. There is no
+/// repair: a save gets here when the editor's tolerant read fails on it, so
+/// it can only be inspected or restored from a backup.
+fn broken_menu(cli: &Cli, entry: &CharEntry) -> Result<bool> {
+    println!("{}: {}", entry.file().display(), entry.health);
+    loop {
+        let action = Select::new("Choose action:", BrokenOpt::iter().collect()).prompt()?;
+        match action {
+            BrokenOpt::Explain => {
+                let explanation = explain(&fs::read(entry.file())?);
+                print!("{explanation}");
+                if let Some(e) = explanation.error {
+                    println!("error: {e}");
+                }
+            }
+            BrokenOpt::Restore => {
+                let root = entry.dir.parent().unwrap_or(Path::new("."));
+                let backups = backups(cli, root);
+                let character = entry.dir.file_name().unwrap_or_default().to_string_lossy();
+                let mut ids: Vec<String> = backups
+                    .list()?
+                    .into_iter()
+                    .filter(|b| b.character == character)
+                    .map(|b| b.id)
+                    .rev()
+                    .collect();
+                if ids.is_empty() {
+                    println!("No backups of {character}");
+                    continue;
+                }
+                ids.push("\u{23CE} Return".to_owned());
+                let choice = Select::new("Restore:", ids.clone()).raw_prompt()?;
+                let Some(b) = backups.find(&ids[choice.index])? else {
+                    continue;
+                };
+                check_protected(cli, &entry.dir, &name_of(&b.dir.join("player.gdc")))?;
+                if in_menu(proceed(cli, &format!("Restore {character} from {}", b.id)))? {
+                    backups.restore(&b)?;
+                    println!("Restored {}", b.id);
+                    return Ok(true);
+                }
+            }
+            BrokenOpt::Return => return Ok(true),
+            BrokenOpt::Exit => return Ok(false),
         }
-        path.clear();
     }
+}
+
+/// Asks before leaving a character with unsaved changes.
+fn leave(session: &Session) -> Result<bool> {
+    if !session.is_dirty() {
+        return Ok(true);
+    }
+    Ok(Confirm::new(&format!(
+        "{} has unsaved changes, leave without saving?",
+        session.char().header.name
+    ))
+    .with_default(false)
+    .prompt()?)
+}
+
+/// Stops a command under `--dry-run`, otherwise asks for a confirmation.
+fn proceed(cli: &Cli, action: &str) -> Result<bool> {
+    if dry_run(cli) {
+        return Ok(false);
+    }
+    confirm(cli, action)?;
+    Ok(true)
+}
+
+fn dry_run(cli: &Cli) -> bool {
+    if cli.dry_run {
+        println!("Dry run, nothing written");
+    }
+    cli.dry_run
+}
 
+/// Declining a change in the menus goes back to them instead of exiting.
+fn in_menu(written: Result<bool>) -> Result<bool> {
+    match written {
+        Err(e) if matches!(e.downcast_ref(), Some(CliError::Cancelled)) => Ok(false),
+        r => r,
+    }
+}
+
+/// Asks before `action` changes a save, unless `--yes` was given.
+fn confirm(cli: &Cli, action: &str) -> Result<()> {
+    if cli.yes {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(CliError::NotConfirmed(action.to_owned()).into());
+    }
+    if !Confirm::new(&format!("{action}?"))
+        .with_default(false)
+        .prompt()?
+    {
+        return Err(CliError::Cancelled.into());
+    }
     Ok(())
 }
 
-fn clone_char(path: &Path, current_char_dir: &Path) -> Result<String> {
-    let to_name = Text::new("Enter a new name:").prompt()?;
+fn find_save_files() -> Result<Vec<(String, PathBuf)>> {
+    let roots = Discovery::from_env().map(|d| d.roots()).unwrap_or_default();
+    if roots.is_empty() {
+        bail!("could not detect save files location")
+    }
+    Ok(roots.into_iter().map(|r| (r.label(), r.path)).collect())
+}
+
+/// Picks one of the labelled save folders. The one of the configured Steam
+/// account wins when there are several, or is preselected when asking.
+fn choose_root(
+    cli: &Cli,
+    mut options: Vec<(String, PathBuf)>,
+    interactive: bool,
+) -> Result<PathBuf> {
+    let preferred = options.iter().position(|(_, p)| {
+        let Some(account) = &cli.config.steam_account else {
+            return false;
+        };
+        let parts: Vec<_> = p.components().map(|c| c.as_os_str()).collect();
+        parts
+            .windows(2)
+            .any(|w| w[0] == "userdata" && w[1] == account.as_str())
+    });
+
+    if options.len() == 1 {
+        return Ok(options.remove(0).1);
+    }
+    if !interactive {
+        return match preferred {
+            Some(i) => Ok(options.remove(i).1),
+            None => Err(CliError::SeveralRoots(
+                options
+                    .iter()
+                    .map(|(_, p)| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+            .into()),
+        };
+    }
+
+    let labels: Vec<&String> = options.iter().map(|(l, _)| l).collect();
+    let chosen = Select::new("Choose save files location:", labels)
+        .with_starting_cursor(preferred.unwrap_or(0))
+        .raw_prompt()?
+        .index;
+    Ok(options.remove(chosen).1)
+}
+
+/// Save folder a clone of the character in `dir` goes to: its own, `main`
+/// or the folder of a mod of the same install.
+fn clone_root(dir: &Path, to: Option<&str>) -> Result<PathBuf> {
+    let root = dir.parent().unwrap_or(Path::new("."));
+    let target = match to {
+        None => return Ok(root.to_path_buf()),
+        Some("main") => game_root(root, None),
+        Some(m) => game_root(root, Some(m)),
+    };
+    target.with_context(|| format!("{} is not in a save folder", dir.display()))
+}
+
+/// Asks for the folder of a clone when the install has mod folders.
+fn choose_clone_root(dir: &Path) -> Result<PathBuf> {
+    let root = dir.parent().unwrap_or(Path::new("."));
+    let Some(main) = game_root(root, None) else {
+        return Ok(root.to_path_buf());
+    };
+    let mut folders = vec![main.clone()];
+    folders.extend(mod_roots(&main));
+    if folders.len() == 1 {
+        return Ok(root.to_path_buf());
+    }
+
+    let labels: Vec<String> = folders
+        .iter()
+        .map(|f| mod_name(f).unwrap_or("main".to_owned()))
+        .collect();
+    let current = folders.iter().position(|f| f == root).unwrap_or(0);
+    let chosen = Select::new("Copy to:", labels)
+        .with_starting_cursor(current)
+        .raw_prompt()?
+        .index;
+    Ok(folders.remove(chosen))
+}
+
+fn clone_char(path: &Path, current_char_dir: &Path, to_name: &str) -> Result<()> {
     let to_char_dir = path.join(Path::new(format!("_{}", &to_name).as_str()));
+    if to_char_dir.exists() {
+        bail!("{} already exists", to_char_dir.display());
+    }
 
+    fs::create_dir_all(path)?;
     copy_dir(current_char_dir, &to_char_dir)?;
 
     let mut cloned_char = char::Char::new();
     let file_path = &to_char_dir.join("player.gdc");
 
-    cloned_char.read(file_path)?;
-    cloned_char.rename(&to_name).save_as(file_path)?;
+    cloned_char.with_tolerant(true).read(file_path)?;
+    cloned_char.rename(to_name).save_as(file_path)?;
 
-    Ok(to_name)
+    Ok(())
 }
@@ -0,0 +1,252 @@
+use crate::util::{data_dir, replace_dir};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use copy_dir::copy_dir;
+use tracing::debug;
+
+/// Snapshot of a character directory, taken before a write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    /// `<character dir>/<UTC time>`, e.g. `_Bob/20261017-093015`.
+    pub id: String,
+    /// Where the snapshot is stored.
+    pub dir: PathBuf,
+    /// Directory name of the character, e.g. `_Bob`.
+    pub character: String,
+}
+
+/// Rotating backups of the characters of a save root.
+///
+/// Snapshots are full copies of a character directory, kept out of the
+/// root so neither the game nor Steam Cloud picks them up: under
+/// `$XDG_DATA_HOME/yagde/backups`, in a folder named after a hash of the
+/// root. Only the newest `keep` snapshots of each character are kept.
+#[derive(Debug, Clone)]
+pub struct Backups {
+    root: PathBuf,
+    store: Option<PathBuf>,
+    keep: usize,
+}
+
+impl Backups {
+    pub const DEFAULT_KEEP: usize = 10;
+
+    pub fn new(root: &Path) -> Self {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let store = data_dir().map(|d| d.join("backups").join(root_key(&root)));
+        Self {
+            root,
+            store,
+            keep: Self::DEFAULT_KEEP,
+        }
+    }
+
+    /// Keeps the snapshots in `store` instead of the data directory.
+    pub fn with_store(&mut self, store: &Path) -> &mut Self {
+        self.store = Some(store.to_path_buf());
+        self
+    }
+
+    /// Number of snapshots kept per character, 0 turns backups off.
+    pub fn with_keep(&mut self, keep: usize) -> &mut Self {
+        self.keep = keep;
+        self
+    }
+
+    /// Where the snapshots are kept, `None` without a home directory.
+    pub fn store(&self) -> Option<&Path> {
+        self.store.as_deref()
+    }
+
+    fn store_or_err(&self) -> io::Result<&Path> {
+        self.store().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no backup location, set XDG_DATA_HOME or HOME",
+            )
+        })
+    }
+
+    /// Snapshots `char_dir`, which has to be in the root, then drops the
+    /// oldest snapshots of that character.
+    pub fn create(&self, char_dir: &Path) -> io::Result<Option<Backup>> {
+        if self.keep == 0 {
+            return Ok(None);
+        }
+        let character = file_name(char_dir)?;
+        let base = self.store_or_err()?.join(&character);
+        fs::create_dir_all(&base)?;
+
+        // numbered after the last snapshot of the same second, even if the
+        // first ones were pruned already
+        let stamp = timestamp(SystemTime::now());
+        let last = self
+            .list_character(&character)?
+            .iter()
+            .map(|b| order(&b.id))
+            .filter(|(s, _)| *s == stamp)
+            .map(|(_, n)| n)
+            .max();
+        let id = match last {
+            Some(n) => format!("{stamp}-{}", n + 1),
+            None => stamp,
+        };
+
+        let dir = base.join(&id);
+        copy_dir(char_dir, &dir)?;
+        debug!(?dir, "backed up {}", char_dir.display());
+
+        self.prune_character(&character)?;
+        Ok(Some(Backup {
+            id: format!("{character}/{id}"),
+            dir,
+            character,
+        }))
+    }
+
+    /// All snapshots, by character and then oldest first.
+    pub fn list(&self) -> io::Result<Vec<Backup>> {
+        let Some(store) = self.store().filter(|s| s.exists()) else {
+            return Ok(Vec::new());
+        };
+
+        let mut characters: Vec<String> = Vec::new();
+        for e in fs::read_dir(store)? {
+            let e = e?;
+            if e.file_type()?.is_dir() {
+                characters.push(e.file_name().to_string_lossy().into_owned());
+            }
+        }
+        characters.sort();
+
+        let mut backups = Vec::new();
+        for c in characters {
+            backups.extend(self.list_character(&c)?);
+        }
+        Ok(backups)
+    }
+
+    pub fn find(&self, id: &str) -> io::Result<Option<Backup>> {
+        Ok(self.list()?.into_iter().find(|b| b.id == id))
+    }
+
+    /// Puts the snapshot back in place of the character directory. The
+    /// current directory is backed up first, so a restore can be undone.
+    pub fn restore(&self, backup: &Backup) -> io::Result<()> {
+        let target = self.root.join(&backup.character);
+        if target.exists() {
+            self.create(&target)?;
+        }
+        replace_dir(&backup.dir, &target)
+    }
+
+    /// Snapshots past the newest `keep` of every character, which `prune`
+    /// would drop.
+    pub fn prunable(&self) -> io::Result<Vec<Backup>> {
+        let mut characters: Vec<String> = self.list()?.into_iter().map(|b| b.character).collect();
+        characters.dedup();
+
+        let mut old = Vec::new();
+        for c in characters {
+            old.extend(self.prunable_character(&c)?);
+        }
+        Ok(old)
+    }
+
+    /// Drops all but the newest `keep` snapshots of every character and
+    /// returns the dropped ones.
+    pub fn prune(&self) -> io::Result<Vec<Backup>> {
+        let old = self.prunable()?;
+        for b in old.iter() {
+            fs::remove_dir_all(&b.dir)?;
+        }
+        Ok(old)
+    }
+
+    fn prune_character(&self, character: &str) -> io::Result<()> {
+        for b in self.prunable_character(character)? {
+            fs::remove_dir_all(&b.dir)?;
+        }
+        Ok(())
+    }
+
+    fn prunable_character(&self, character: &str) -> io::Result<Vec<Backup>> {
+        let mut backups = self.list_character(character)?;
+        let extra = backups.len().saturating_sub(self.keep.max(1));
+        backups.truncate(extra);
+        Ok(backups)
+    }
+
+    fn list_character(&self, character: &str) -> io::Result<Vec<Backup>> {
+        let mut backups = Vec::new();
+        for e in fs::read_dir(self.store_or_err()?.join(character))? {
+            let e = e?;
+            if e.file_type()?.is_dir() {
+                let id = e.file_name().to_string_lossy().into_owned();
+                backups.push(Backup {
+                    id: format!("{character}/{id}"),
+                    dir: e.path(),
+                    character: character.to_owned(),
+                });
+            }
+        }
+        backups.sort_by(|a, b| order(&a.id).cmp(&order(&b.id)));
+        Ok(backups)
+    }
+}
+
+/// Folder name of the snapshots of `root`: FNV-1a of its path, which unlike
+/// `DefaultHasher` stays the same across Rust releases.
+fn root_key(root: &Path) -> String {
+    let hash = root
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+            (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{hash:016x}")
+}
+
+fn file_name(path: &Path) -> io::Result<String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))
+}
+
+// snapshots of the same second get a `-2`, `-3`... suffix, which would not
+// sort right as text past 9
+fn order(id: &str) -> (&str, u32) {
+    let stamp = id.rsplit('/').next().unwrap_or(id);
+    match (stamp.get(..15), stamp.get(16..)) {
+        (Some(base), Some(n)) => (base, n.parse().unwrap_or(1)),
+        _ => (stamp, 1),
+    }
+}
+
+/// UTC time as `YYYYMMDD-HHMMSS`.
+pub(crate) fn timestamp(t: SystemTime) -> String {
+    let secs = t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // days to civil date, from Howard Hinnant's date algorithms
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
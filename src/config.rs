@@ -0,0 +1,186 @@
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("failed to read the config: {0}")]
+    Io(#[from] io::Error),
+    #[cfg(feature = "serde")]
+    #[error("failed to parse the config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[cfg(feature = "serde")]
+    #[error("failed to write the config: {0}")]
+    Write(#[from] toml::ser::Error),
+    #[error("config files need the serde feature")]
+    Unsupported,
+    #[error("no config file location, set XDG_CONFIG_HOME or HOME")]
+    NoPath,
+    #[error("unknown setting {0}, expected one of {keys}", keys = Config::KEYS.join(", "))]
+    UnknownKey(String),
+    #[error("invalid value {value:?} for {key}: {reason}")]
+    InvalidValue {
+        key: &'static str,
+        value: String,
+        reason: &'static str,
+    },
+}
+
+/// Settings of `$XDG_CONFIG_HOME/yagde/config.toml`. Everything is optional,
+/// command line options win over the config.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct Config {
+    /// Save folders to use instead of looking for them, `~` stands for the
+    /// home directory.
+    pub save_roots: Vec<String>,
+    /// Steam account id, as in `userdata/<id>`, whose cloud saves are picked
+    /// when several save folders are found.
+    pub steam_account: Option<String>,
+    /// Backups kept per character.
+    pub keep_backups: Option<usize>,
+    /// `text` or `json`, for `list` and `view`.
+    pub output: Option<String>,
+    /// Names or directories of characters that are never written.
+    pub protected: Vec<String>,
+}
+
+impl Config {
+    pub const KEYS: &'static [&'static str] = &[
+        "save_roots",
+        "steam_account",
+        "keep_backups",
+        "output",
+        "protected",
+    ];
+
+    /// `$XDG_CONFIG_HOME/yagde/config.toml`, or `~/.config/yagde/config.toml`.
+    pub fn path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("yagde").join("config.toml"))
+    }
+
+    /// Reads the config, a missing file is an empty config.
+    #[cfg(feature = "serde")]
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Without serde there is no parser, so an existing file fails rather
+    /// than being ignored along with its protected characters.
+    #[cfg(not(feature = "serde"))]
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match path.try_exists()? {
+            true => Err(ConfigError::Unsupported),
+            false => Ok(Self::default()),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        crate::util::write_atomic(path, toml::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn save(&self, _path: &Path) -> Result<(), ConfigError> {
+        Err(ConfigError::Unsupported)
+    }
+
+    /// A setting as text, lists are comma separated and unset values empty.
+    pub fn get(&self, key: &str) -> Result<String, ConfigError> {
+        Ok(match key {
+            "save_roots" => self.save_roots.join(","),
+            "steam_account" => self.steam_account.clone().unwrap_or_default(),
+            "keep_backups" => self.keep_backups.map(|k| k.to_string()).unwrap_or_default(),
+            "output" => self.output.clone().unwrap_or_default(),
+            "protected" => self.protected.join(","),
+            _ => return Err(ConfigError::UnknownKey(key.to_owned())),
+        })
+    }
+
+    /// Changes a setting from text as `get` returns it. An empty value
+    /// unsets it.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let list = || {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_owned)
+                .collect()
+        };
+        let invalid = |key, reason| ConfigError::InvalidValue {
+            key,
+            value: value.to_owned(),
+            reason,
+        };
+        let value = value.trim();
+
+        match key {
+            "save_roots" => self.save_roots = list(),
+            "protected" => self.protected = list(),
+            _ if value.is_empty() && Self::KEYS.contains(&key) => match key {
+                "steam_account" => self.steam_account = None,
+                "keep_backups" => self.keep_backups = None,
+                _ => self.output = None,
+            },
+            "steam_account" if value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.steam_account = Some(value.to_owned())
+            }
+            "steam_account" => return Err(invalid("steam_account", "expected a numeric id")),
+            "keep_backups" => {
+                self.keep_backups = Some(
+                    value
+                        .parse()
+                        .map_err(|_| invalid("keep_backups", "expected a count"))?,
+                )
+            }
+            "output" if value == "text" || value == "json" => self.output = Some(value.to_owned()),
+            "output" => return Err(invalid("output", "expected text or json")),
+            _ => return Err(ConfigError::UnknownKey(key.to_owned())),
+        }
+        Ok(())
+    }
+
+    /// Whether the character named `name`, stored in `dir`, is protected.
+    pub fn is_protected(&self, dir: &Path, name: &str) -> bool {
+        let dir_name = dir.file_name().map(|n| n.to_string_lossy());
+        self.protected
+            .iter()
+            .any(|p| p == name || dir_name.as_deref() == Some(p.as_str()))
+    }
+
+    /// The save roots with `~` and `$HOME` expanded.
+    pub fn save_roots(&self) -> Vec<PathBuf> {
+        self.save_roots.iter().map(|r| expand_home(r)).collect()
+    }
+}
+
+/// Expands a leading `~` and every `$HOME` in `path`.
+pub fn expand_home(path: &str) -> PathBuf {
+    let Some(home) = env::var_os("HOME") else {
+        return PathBuf::from(path);
+    };
+    let home = home.to_string_lossy();
+    let path = path.replace("$HOME", &home);
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(format!("{home}{rest}"))
+        }
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,367 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+/// Steam app id of Grim Dawn.
+pub const APP_ID: &str = "219990";
+
+/// Where the saves of a Wine prefix live, below `drive_c/users/<user>`.
+const PREFIX_SAVES: &str = "Documents/My Games/Grim Dawn/save/main";
+/// Where Steam Cloud keeps the saves, below `userdata/<account>`.
+const CLOUD_SAVES: &str = "219990/remote/save/main";
+
+/// Steam installs, native, Debian's and the Flatpak.
+const STEAM_ROOTS: &[&str] = &[
+    ".local/share/Steam",
+    ".steam/steam",
+    ".steam/debian-installation",
+    ".var/app/com.valvesoftware.Steam/data/Steam",
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+];
+const LUTRIS_CONFIGS: &[&str] = &[
+    ".config/lutris/games",
+    ".var/app/net.lutris.Lutris/config/lutris/games",
+];
+const HEROIC_CONFIGS: &[&str] = &[
+    ".config/heroic/GamesConfig",
+    ".var/app/com.heroicgameslauncher.hgl/config/heroic/GamesConfig",
+];
+const HEROIC_PREFIXES: &[&str] = &["Games/Heroic/Prefixes", "Games/Heroic/Prefixes/default"];
+const BOTTLES: &[&str] = &[
+    ".local/share/bottles/bottles",
+    ".var/app/com.usebottles.bottles/data/bottles/bottles",
+];
+
+/// How a save root was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Proton prefix of a Steam library.
+    Steam {
+        library: PathBuf,
+    },
+    /// Steam Cloud copy of an account.
+    SteamCloud {
+        account: String,
+    },
+    /// Wine prefix of a Lutris game.
+    Lutris {
+        prefix: PathBuf,
+    },
+    /// Wine prefix of a Heroic (GOG) game.
+    Heroic {
+        prefix: PathBuf,
+    },
+    Bottles {
+        bottle: String,
+    },
+    /// `WINEPREFIX`, or the default `~/.wine`.
+    Wine {
+        prefix: PathBuf,
+    },
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Steam { library } => write!(f, "Steam library {}", library.display()),
+            Source::SteamCloud { account } => write!(f, "Steam Cloud account {account}"),
+            Source::Lutris { prefix } => write!(f, "Lutris {}", prefix.display()),
+            Source::Heroic { prefix } => write!(f, "Heroic {}", prefix.display()),
+            Source::Bottles { bottle } => write!(f, "Bottles {bottle}"),
+            Source::Wine { prefix } => write!(f, "Wine {}", prefix.display()),
+        }
+    }
+}
+
+/// A `save/main` or `save/user/<mod>` folder found on this machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveRoot {
+    pub path: PathBuf,
+    pub source: Source,
+    /// Custom game or mod the saves belong to, `None` for the main game.
+    pub mod_name: Option<String>,
+    /// Character directories in it.
+    pub chars: usize,
+}
+
+impl SaveRoot {
+    /// As shown in menus, e.g. `Steam Cloud account 123, mod Foo (4 chars)`.
+    pub fn label(&self) -> String {
+        match &self.mod_name {
+            Some(m) => format!("{}, mod {m} ({} chars)", self.source, self.chars),
+            None => format!("{} ({} chars)", self.source, self.chars),
+        }
+    }
+}
+
+/// Looks for Grim Dawn saves in the usual places of a Linux home.
+///
+/// Every Steam library of `libraryfolders.vdf`, Steam Cloud accounts,
+/// Lutris, Heroic and Bottles prefixes and plain Wine prefixes are probed.
+/// Locations that don't exist or can't be read are skipped. Mod folders
+/// come right after the main folder they belong to.
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    home: PathBuf,
+    wine_prefixes: Vec<PathBuf>,
+}
+
+impl Discovery {
+    pub fn new(home: &Path) -> Self {
+        Self {
+            home: home.to_path_buf(),
+            wine_prefixes: vec![home.join(".wine")],
+        }
+    }
+
+    /// Discovery of `$HOME`, with `$WINEPREFIX` if set.
+    pub fn from_env() -> Option<Self> {
+        let mut d = Self::new(Path::new(&env::var_os("HOME")?));
+        if let Some(prefix) = env::var_os("WINEPREFIX").filter(|p| !p.is_empty()) {
+            d.with_wine_prefix(Path::new(&prefix));
+        }
+        Some(d)
+    }
+
+    /// Also probes this Wine prefix.
+    pub fn with_wine_prefix(&mut self, prefix: &Path) -> &mut Self {
+        self.wine_prefixes.push(prefix.to_path_buf());
+        self
+    }
+
+    /// All save roots found, each once even if reached in several ways.
+    pub fn roots(&self) -> Vec<SaveRoot> {
+        let mut found: Vec<(PathBuf, Source)> = Vec::new();
+        self.steam(&mut found);
+        self.lutris(&mut found);
+        self.heroic(&mut found);
+        self.bottles(&mut found);
+        for prefix in self.wine_prefixes.iter() {
+            found.push((
+                prefix.clone(),
+                Source::Wine {
+                    prefix: prefix.clone(),
+                },
+            ));
+        }
+
+        let mut roots: Vec<SaveRoot> = Vec::new();
+        let mut seen: Vec<PathBuf> = Vec::new();
+        for (dir, source) in found {
+            let mains = match source {
+                Source::SteamCloud { .. } => vec![dir],
+                _ => prefix_saves(&dir),
+            };
+            let paths = mains.into_iter().flat_map(|main| {
+                let mods = mod_roots(&main);
+                Some(main).filter(|m| m.is_dir()).into_iter().chain(mods)
+            });
+            for path in paths {
+                let Ok(real) = path.canonicalize() else {
+                    continue;
+                };
+                if seen.contains(&real) {
+                    continue;
+                }
+                debug!(?path, %source, "found saves");
+                seen.push(real);
+                roots.push(SaveRoot {
+                    mod_name: mod_name(&path),
+                    chars: count_chars(&path),
+                    path,
+                    source: source.clone(),
+                });
+            }
+        }
+        roots
+    }
+
+    fn steam(&self, found: &mut Vec<(PathBuf, Source)>) {
+        let mut libraries: Vec<PathBuf> = Vec::new();
+        for root in STEAM_ROOTS.iter().map(|r| self.home.join(r)) {
+            if !root.is_dir() {
+                continue;
+            }
+            libraries.push(root.clone());
+            let vdf = root.join("steamapps/libraryfolders.vdf");
+            if let Ok(text) = fs::read_to_string(&vdf) {
+                libraries.extend(library_folders(&text));
+            }
+
+            for account in read_dirs(&root.join("userdata")) {
+                let path = account.join(CLOUD_SAVES);
+                if path.is_dir() || !mod_roots(&path).is_empty() {
+                    let account = file_name(&account);
+                    found.push((path, Source::SteamCloud { account }));
+                }
+            }
+        }
+
+        for library in libraries {
+            let prefix = library
+                .join("steamapps/compatdata")
+                .join(APP_ID)
+                .join("pfx");
+            found.push((prefix, Source::Steam { library }));
+        }
+    }
+
+    fn lutris(&self, found: &mut Vec<(PathBuf, Source)>) {
+        for dir in LUTRIS_CONFIGS.iter().map(|c| self.home.join(c)) {
+            for config in read_files(&dir) {
+                let Ok(text) = fs::read_to_string(&config) else {
+                    continue;
+                };
+                if let Some(prefix) = yaml_value(&text, "prefix") {
+                    let prefix = PathBuf::from(prefix);
+                    found.push((prefix.clone(), Source::Lutris { prefix }));
+                }
+            }
+        }
+        // the default location of Lutris installs
+        for prefix in read_dirs(&self.home.join("Games")) {
+            found.push((prefix.clone(), Source::Lutris { prefix }));
+        }
+    }
+
+    fn heroic(&self, found: &mut Vec<(PathBuf, Source)>) {
+        for dir in HEROIC_CONFIGS.iter().map(|c| self.home.join(c)) {
+            for config in read_files(&dir) {
+                let Ok(text) = fs::read_to_string(&config) else {
+                    continue;
+                };
+                for prefix in json_values(&text, "winePrefix") {
+                    let prefix = PathBuf::from(prefix);
+                    found.push((prefix.clone(), Source::Heroic { prefix }));
+                }
+            }
+        }
+        for dir in HEROIC_PREFIXES.iter().map(|p| self.home.join(p)) {
+            for prefix in read_dirs(&dir) {
+                found.push((prefix.clone(), Source::Heroic { prefix }));
+            }
+        }
+    }
+
+    fn bottles(&self, found: &mut Vec<(PathBuf, Source)>) {
+        for dir in BOTTLES.iter().map(|b| self.home.join(b)) {
+            for bottle in read_dirs(&dir) {
+                let name = file_name(&bottle);
+                found.push((bottle, Source::Bottles { bottle: name }));
+            }
+        }
+    }
+}
+
+/// Library paths of a Steam `libraryfolders.vdf`.
+pub fn library_folders(vdf: &str) -> Vec<PathBuf> {
+    vdf.lines()
+        .filter_map(|line| {
+            let mut parts = line.split('"').skip(1).step_by(2);
+            match (parts.next(), parts.next()) {
+                (Some("path"), Some(path)) => Some(PathBuf::from(path.replace("\\\\", "\\"))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Mod folders next to a `save/main` folder, i.e. `save/user/*`.
+pub fn mod_roots(main: &Path) -> Vec<PathBuf> {
+    match main.parent() {
+        Some(save) if main.ends_with("main") => read_dirs(&save.join("user")),
+        _ => Vec::new(),
+    }
+}
+
+/// Name of the mod whose saves are in `root`, `None` for the main game.
+pub fn mod_name(root: &Path) -> Option<String> {
+    let parent = root.parent()?;
+    parent.ends_with("user").then(|| file_name(root))
+}
+
+/// The folder of `mod_name`, or `main` without one, of the same install as
+/// the save root `root`.
+pub fn game_root(root: &Path, mod_name: Option<&str>) -> Option<PathBuf> {
+    let save = match self::mod_name(root) {
+        Some(_) => root.parent()?.parent()?,
+        None => root.parent()?,
+    };
+    Some(match mod_name {
+        Some(m) => save.join("user").join(m),
+        None => save.join("main"),
+    })
+}
+
+/// Main save folders of a Wine prefix, one per Windows user.
+fn prefix_saves(prefix: &Path) -> Vec<PathBuf> {
+    read_dirs(&prefix.join("drive_c/users"))
+        .into_iter()
+        .map(|user| user.join(PREFIX_SAVES))
+        .filter(|p| p.is_dir() || !mod_roots(p).is_empty())
+        .collect()
+}
+
+fn count_chars(root: &Path) -> usize {
+    read_dirs(root)
+        .iter()
+        .filter(|d| !file_name(d).contains("__"))
+        .count()
+}
+
+/// Sub directories of `dir`, sorted, none if it can't be read.
+fn read_dirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    dirs.sort();
+    dirs
+}
+
+fn read_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Value of a top level or nested `key: value` line of a Lutris game config.
+fn yaml_value<'a>(yaml: &'a str, key: &str) -> Option<&'a str> {
+    yaml.lines().find_map(|line| {
+        let value = line.trim().strip_prefix(key)?.strip_prefix(':')?.trim();
+        Some(value.trim_matches(|c| c == '"' || c == '\'')).filter(|v| !v.is_empty())
+    })
+}
+
+/// String values of `"key": "value"` pairs, wherever they are nested.
+fn json_values<'a>(json: &'a str, key: &str) -> Vec<&'a str> {
+    let pattern = format!("\"{key}\"");
+    json.match_indices(&pattern)
+        .filter_map(|(i, _)| {
+            let rest = json[i + pattern.len()..].trim_start().strip_prefix(':')?;
+            let rest = rest.trim_start().strip_prefix('"')?;
+            rest.split('"').next()
+        })
+        .filter(|v| !v.is_empty())
+        .collect()
+}
@@ -1,29 +1,28 @@
-use crate::gd::gd_file::{GDFile, GDReader, GDWriter, ReadWrite};
+use crate::gd::error::{Error, ErrorKind, Location, Result};
+use crate::gd::gd_file::{
+    BlockSpan, GDBuf, GDFile, GDReader, GDWriter, GdBlock, Limits, OpaqueBlock, ReadWrite,
+};
 use crate::gd::info::{Bio, Info};
 use crate::gd::inventory::*;
 use crate::gd::lists::*;
 use crate::gd::misc::*;
 use crate::gd::skills::*;
 use crate::gd::stats::*;
+use crate::util::write_atomic;
 
-use std::fs::File;
-use std::path::PathBuf;
+use std::collections::hash_map::RandomState;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Cursor, Read, Seek, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::{bail, Context, Error, Ok, Result};
 use smart_default::SmartDefault;
 use strum_macros::Display;
-use thiserror::Error;
 use tracing::{debug, instrument};
 
-#[derive(Error, Debug)]
-enum ParseError {
-    #[error("Parse sex error: {0}")]
-    ParseSexError(u8),
-    #[error("Parse expansion status: {0}")]
-    ParseExpansionStatusError(u8),
-}
-
 #[derive(SmartDefault, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Char {
     pub header: Header,
     pub stats: Stats,
@@ -43,15 +42,170 @@ pub struct Char {
     pub ui: UI,
     pub tutorials: TutorialPages,
     pub crucible: Crucible,
-    #[default(_code = "vec![6, 7, 8]")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    opaque: Vec<OpaqueBlock>,
+    // reading options, not part of the character
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tolerant: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    limits: Limits,
+    seed: u32,
+    #[default(_code = "Self::VERSIONS.to_vec()")]
+    #[cfg_attr(feature = "serde", serde(skip))]
     supported_versions: Vec<u32>,
 }
 
 impl Char {
+    /// Known character versions, oldest first, like `GdBlock::VERSIONS`.
+    pub const VERSIONS: &'static [u32] = &[6, 7, 8];
+
     pub fn new() -> Self {
         Default::default()
     }
 
+    /// Seed the character will be encrypted with when written. Characters
+    /// read from disk keep the seed of their source file, so game-written
+    /// saves stay encrypted and plain ones (seed 0) stay plain.
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn with_seed(&mut self, seed: u32) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Picks a fresh non-zero seed, the way the game does on every save.
+    pub fn with_random_seed(&mut self) -> &mut Self {
+        let mut seed = 0;
+        while seed == 0 {
+            seed = RandomState::new().build_hasher().finish() as u32;
+        }
+        self.with_seed(seed)
+    }
+
+    /// In tolerant mode blocks with an unknown version are kept as opaque
+    /// bytes instead of failing the whole read. They are written back as is.
+    pub fn with_tolerant(&mut self, tolerant: bool) -> &mut Self {
+        self.tolerant = tolerant;
+        self
+    }
+
+    /// Bounds for lengths read from the file. Saves going over them are
+    /// rejected as corrupt.
+    pub fn with_limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Blocks skipped by a tolerant read.
+    pub fn opaque_blocks(&self) -> &[OpaqueBlock] {
+        &self.opaque
+    }
+
+    /// Whether edits are limited to what comes after the opaque blocks. Those
+    /// stay encrypted with the key stream of the source file, which any
+    /// change before them moves on; plain text saves don't have one.
+    pub fn is_read_only(&self) -> bool {
+        self.seed != 0 && !self.opaque.is_empty()
+    }
+
+    /// Fails the way writing would, with `OpaqueBlockKeyMismatch`, when an
+    /// edit of a read-only character can't be written back.
+    pub fn check_writable(&self) -> Result<()> {
+        if !self.is_read_only() {
+            return Ok(());
+        }
+        self.to_writer(io::sink())
+    }
+
+    /// Versions the character and its blocks are currently laid out in.
+    pub fn format(&self) -> Format {
+        Format {
+            header: self.header.version,
+            char: self.version,
+            info: self.info.version(),
+            inventory: self.inventory.version(),
+            stash: self.stash.version(),
+            skills: self.skills.version(),
+            ui: self.ui.version(),
+            stats: self.stats.version(),
+        }
+    }
+
+    /// Converts the character to the layout of `to`, e.g. `Format::LATEST`
+    /// or the format of a character saved by another install. Values the
+    /// new layout has no room for are dropped, and their names returned in
+    /// the `Info.loot_filters` form; new values start out empty. The
+    /// character is left untouched if the conversion fails.
+    pub fn migrate(&mut self, to: &Format) -> Result<Vec<String>> {
+        if let Some(b) = self.opaque.first() {
+            return Err(Error::new(
+                ErrorKind::OpaqueBlockMigration(b.seq),
+                Location {
+                    block_seq: Some(b.seq),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        let mut c = self.clone();
+        let mut lost = Vec::new();
+
+        if !self.supported_versions.contains(&to.char) {
+            return Err(Error::new(
+                ErrorKind::UnsupportedVersion {
+                    actual: to.char,
+                    expected: self.supported_versions.clone(),
+                },
+                Location::default(),
+            ));
+        }
+        // the crucible block was added in version 7
+        if to.char >= 7 {
+            migrate_block(
+                &mut c.crucible,
+                Format::latest(Crucible::VERSIONS),
+                &mut lost,
+            )?;
+        } else {
+            if c.crucible
+                .tokens_per_difficulty
+                .iter()
+                .any(|t| !t.is_empty())
+            {
+                lost.push(Crucible::NAME.to_owned());
+            }
+            c.crucible = Crucible::default();
+        }
+        c.version = to.char;
+
+        lost.extend(
+            c.header
+                .migrate(to.header)
+                .map_err(|k| {
+                    Error::new(
+                        k,
+                        Location {
+                            path: vec!["Header".to_owned()],
+                            ..Default::default()
+                        },
+                    )
+                })?
+                .into_iter()
+                .map(|f| format!("Header.{f}")),
+        );
+        migrate_block(&mut c.info, to.info, &mut lost)?;
+        migrate_block(&mut c.inventory, to.inventory, &mut lost)?;
+        migrate_block(&mut c.stash, to.stash, &mut lost)?;
+        migrate_block(&mut c.skills, to.skills, &mut lost)?;
+        migrate_block(&mut c.ui, to.ui, &mut lost)?;
+        migrate_block(&mut c.stats, to.stats, &mut lost)?;
+
+        *self = c;
+        Ok(lost)
+    }
+
     pub fn rename(&mut self, new_name: &str) -> &mut Self {
         new_name.clone_into(&mut self.header.name);
         self
@@ -66,6 +220,12 @@ impl Char {
         self
     }
 
+    /// Adds iron, stopping at the most the save can hold.
+    pub fn add_money(&mut self, amount: u32) -> &mut Self {
+        self.info.money = self.info.money.saturating_add(amount);
+        self
+    }
+
     pub fn boost_frendly_factions(&mut self) -> &mut Self {
         for f in self.factions.factions.iter_mut() {
             if f.value > 0.0 {
@@ -210,91 +370,328 @@ impl Char {
             "{0: <35} {1}",
             "Devotion reclamation points used:", &self.skills.devotion_reclamation_points_used
         );
+        if !self.opaque.is_empty() {
+            println!("{:=^50}", " Skipped blocks ");
+            for b in self.opaque.iter() {
+                println!(
+                    "{0: <35} version {1}, {2} bytes",
+                    format!("{}:", block_name(b.seq)),
+                    b.version,
+                    b.len()
+                );
+            }
+            if self.is_read_only() {
+                println!(
+                    "Read-only: the save is encrypted, edits before these blocks can't be saved"
+                );
+            }
+        }
         println!("{:=^50}", " End ");
         println!();
     }
 
-    pub fn get_name(&mut self, path: &PathBuf) -> Result<String> {
-        let mut f = GDFile::new(File::open(path)?);
-
-        f.validate()?;
-        self.header.read(&mut f)?;
+    pub fn get_name(&mut self, path: &Path) -> Result<String> {
+        self.header = Self::read_header(path)?;
 
         Ok(self.header.name.to_string())
     }
 
+    /// Reads only the header of a save. Just the beginning of the file is
+    /// loaded, the rest is read only if the header doesn't fit into it.
+    pub fn read_header(path: &Path) -> Result<Header> {
+        const PREFIX_LEN: u64 = 4096;
+
+        let mut data = Vec::new();
+        File::open(path)?.take(PREFIX_LEN).read_to_end(&mut data)?;
+
+        let parse = |data: &[u8]| -> Result<Header> {
+            let mut header = Header::default();
+            let mut f = GDBuf::new(data);
+            f.validate()?;
+            header.read(&mut f)?;
+            Ok(header)
+        };
+
+        match parse(&data) {
+            Err(_) if data.len() as u64 == PREFIX_LEN => parse(&fs::read(path)?),
+            r => r,
+        }
+    }
+
+    /// Writes the save through a temporary file, so `path` is either left
+    /// as it was or fully replaced.
     #[instrument(skip(self))]
     pub fn write(&mut self, path: &PathBuf) -> Result<()> {
-        let mut f = GDFile::new(File::create(path)?);
+        let mut data = Vec::new();
+        self.to_writer(&mut data)?;
+        Ok(write_atomic(path, &data)?)
+    }
 
-        f.write_int(1431655765)?;
+    #[instrument(skip(self))]
+    pub fn read(&mut self, path: &PathBuf) -> Result<&mut Self> {
+        self.read_bytes(&fs::read(path)?)
+    }
+
+    /// Parses a character from any byte source, e.g. an in-memory buffer,
+    /// an archive entry or stdin. The source is read to the end once and
+    /// decoded from memory.
+    pub fn from_reader<R: Read>(r: R) -> Result<Self> {
+        let mut c = Self::new();
+        c.read_from(r)?;
+        Ok(c)
+    }
+
+    /// Like `read`, but takes the save from any byte source.
+    pub fn read_from<R: Read>(&mut self, mut r: R) -> Result<&mut Self> {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+        self.read_bytes(&data)
+    }
+
+    /// Serializes the character into any byte sink. The file is encoded in
+    /// memory and handed to the sink with a single write.
+    pub fn to_writer<W: Write>(&self, mut w: W) -> Result<()> {
+        let mut f = GDFile::new(Cursor::new(Vec::new()));
+
+        f.write_key(self.seed)?;
         f.write_int(1480803399)?;
         self.header.write(&mut f)?;
-        f.write_int(0)?;
+        f.write_next_int(0)?;
         f.write_int(self.version)?;
         self.uid.write(&mut f)?;
-        self.info.write(&mut f)?;
-        self.bio.write(&mut f)?;
-        self.inventory.write(&mut f)?;
-        self.stash.write(&mut f)?;
-        self.respawns.write(&mut f)?;
-        self.teleports.write(&mut f)?;
-        self.markers.write(&mut f)?;
-        self.shrines.write(&mut f)?;
-        self.skills.write(&mut f)?;
-        self.notes.write(&mut f)?;
-        self.factions.write(&mut f)?;
-        self.ui.write(&mut f)?;
-        self.tutorials.write(&mut f)?;
-        self.stats.write(&mut f)?;
+        self.write_block(&mut f, Info::SEQ, |f| self.info.write(f))?;
+        self.write_block(&mut f, Bio::SEQ, |f| self.bio.write(f))?;
+        self.write_block(&mut f, Inventory::SEQ, |f| self.inventory.write(f))?;
+        self.write_block(&mut f, Stash::SEQ, |f| self.stash.write(f))?;
+        self.write_block(&mut f, RespawnList::SEQ, |f| self.respawns.write(f))?;
+        self.write_block(&mut f, TeleportList::SEQ, |f| self.teleports.write(f))?;
+        self.write_block(&mut f, MarkerList::SEQ, |f| self.markers.write(f))?;
+        self.write_block(&mut f, ShrineList::SEQ, |f| self.shrines.write(f))?;
+        self.write_block(&mut f, SkillList::SEQ, |f| self.skills.write(f))?;
+        self.write_block(&mut f, NoteList::SEQ, |f| self.notes.write(f))?;
+        self.write_block(&mut f, FactionList::SEQ, |f| self.factions.write(f))?;
+        self.write_block(&mut f, UI::SEQ, |f| self.ui.write(f))?;
+        self.write_block(&mut f, TutorialPages::SEQ, |f| self.tutorials.write(f))?;
+        self.write_block(&mut f, Stats::SEQ, |f| self.stats.write(f))?;
         if self.version >= 7 {
-            self.crucible.write(&mut f)?;
+            self.write_block(&mut f, Crucible::SEQ, |f| self.crucible.write(f))?;
         }
 
+        w.write_all(&f.into_inner().into_inner())?;
         Ok(())
     }
 
-    #[instrument(skip(self))]
-    pub fn read(&mut self, path: &PathBuf) -> Result<&mut Self> {
-        let mut f = GDFile::new(std::fs::File::open(path)?);
+    /// Reads `data`, writes the character back untouched and checks that
+    /// both byte streams are identical.
+    ///
+    /// The output is encrypted with the seed of the input, so the key stream
+    /// stays in sync up to the first differing byte: the first raw mismatch
+    /// is also the first mismatch of the decrypted streams.
+    pub fn verify_roundtrip(data: &[u8]) -> Result<()> {
+        let mut c = Self::new();
+        let mut f = GDBuf::new(data);
+        c.read_buf(&mut f)?;
+
+        let mut out = Vec::with_capacity(data.len());
+        c.to_writer(&mut out)?;
+
+        let offset = data
+            .iter()
+            .zip(out.iter())
+            .position(|(a, b)| a != b)
+            .unwrap_or(data.len().min(out.len()));
+
+        if offset < data.len() || offset < out.len() {
+            return Err(Error::new(
+                ErrorKind::RoundtripMismatch {
+                    expected: data.get(offset).copied(),
+                    actual: out.get(offset).copied(),
+                },
+                location_at(f.spans(), offset as u64),
+            ));
+        }
 
-        f.validate()?;
-        self.header.read(&mut f)?;
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, data: &[u8]) -> Result<&mut Self> {
+        self.read_buf(GDBuf::new(data).with_limits(self.limits))
+    }
 
-        if f.next_int()? != 0 {
-            bail!("next_int() != 0");
+    pub(crate) fn read_buf(&mut self, f: &mut GDBuf) -> Result<&mut Self> {
+        self.opaque.clear();
+
+        f.validate()?;
+        self.seed = f.seed();
+        self.header.read(f)?;
+
+        let ni = f.field("header_end").next_int()?;
+        if ni != 0 {
+            return Err(f.error(ErrorKind::UnexpectedValue {
+                what: "header end",
+                actual: ni,
+                expected: 0,
+            }));
         }
 
         debug!("reading version");
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in char")?;
-        self.uid.read(&mut f)?;
-        self.info.read(&mut f)?;
-        self.bio.read(&mut f)?;
-        self.inventory.read(&mut f)?;
-        self.stash.read(&mut f)?;
-
-        self.respawns.read(&mut f)?;
-        self.teleports.read(&mut f)?;
-        self.markers.read(&mut f)?;
-        self.shrines.read(&mut f)?;
-
-        self.skills.read(&mut f)?;
-        self.notes.read(&mut f)?;
-        self.factions.read(&mut f)?;
-        self.ui.read(&mut f)?;
-        self.tutorials.read(&mut f)?;
-        self.stats.read(&mut f)?;
+        self.version = f.read_version(&self.supported_versions)?;
+        f.enter("uid");
+        self.uid.read(f)?;
+        f.leave();
+        self.read_block(f, Info::SEQ, |c, f| c.info.read(f))?;
+        self.read_block(f, Bio::SEQ, |c, f| c.bio.read(f))?;
+        self.read_block(f, Inventory::SEQ, |c, f| c.inventory.read(f))?;
+        self.read_block(f, Stash::SEQ, |c, f| c.stash.read(f))?;
+
+        self.read_block(f, RespawnList::SEQ, |c, f| c.respawns.read(f))?;
+        self.read_block(f, TeleportList::SEQ, |c, f| c.teleports.read(f))?;
+        self.read_block(f, MarkerList::SEQ, |c, f| c.markers.read(f))?;
+        self.read_block(f, ShrineList::SEQ, |c, f| c.shrines.read(f))?;
+
+        self.read_block(f, SkillList::SEQ, |c, f| c.skills.read(f))?;
+        self.read_block(f, NoteList::SEQ, |c, f| c.notes.read(f))?;
+        self.read_block(f, FactionList::SEQ, |c, f| c.factions.read(f))?;
+        self.read_block(f, UI::SEQ, |c, f| c.ui.read(f))?;
+        self.read_block(f, TutorialPages::SEQ, |c, f| c.tutorials.read(f))?;
+        self.read_block(f, Stats::SEQ, |c, f| c.stats.read(f))?;
         if self.version >= 7 {
-            self.crucible.read(&mut f)?;
+            self.read_block(f, Crucible::SEQ, |c, f| c.crucible.read(f))?;
+        } else {
+            self.crucible = Crucible::default();
         }
 
         Ok(self)
     }
+
+    /// Reads block `seq`, falling back to an opaque copy of it in tolerant
+    /// mode when its version isn't supported.
+    fn read_block(
+        &mut self,
+        f: &mut GDBuf,
+        seq: u32,
+        read: impl FnOnce(&mut Self, &mut GDBuf) -> Result<()>,
+    ) -> Result<()> {
+        let mark = f.mark();
+        f.enter(block_name(seq));
+        let res = match read(self, f) {
+            Err(e) if self.tolerant && matches!(e.kind, ErrorKind::UnsupportedVersion { .. }) => {
+                debug!(seq, "keeping block as opaque: {e}");
+                let b = f.read_opaque_block(mark, seq)?;
+                self.opaque.push(b);
+                Ok(())
+            }
+            r => r,
+        };
+        f.leave();
+        res
+    }
+
+    fn write_block<W: Write + Seek>(
+        &self,
+        f: &mut GDFile<W>,
+        seq: u32,
+        write: impl FnOnce(&mut GDFile<W>) -> Result<()>,
+    ) -> Result<()> {
+        match self.opaque.iter().find(|b| b.seq == seq) {
+            Some(b) => f.write_opaque_block(b),
+            None => write(f),
+        }
+    }
+}
+
+/// Save layout written by a game patch: the version of the character and of
+/// every block that has more than one layout. Blocks with a single known
+/// version are the same everywhere and not listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    pub header: u32,
+    pub char: u32,
+    pub info: u32,
+    pub inventory: u32,
+    pub stash: u32,
+    pub skills: u32,
+    pub ui: u32,
+    pub stats: u32,
+}
+
+impl Format {
+    /// Newest layout yagde knows about.
+    pub const LATEST: Format = Format {
+        header: Self::latest(Header::VERSIONS),
+        char: Self::latest(Char::VERSIONS),
+        info: Self::latest(Info::VERSIONS),
+        inventory: Self::latest(Inventory::VERSIONS),
+        stash: Self::latest(Stash::VERSIONS),
+        skills: Self::latest(SkillList::VERSIONS),
+        ui: Self::latest(UI::VERSIONS),
+        stats: Self::latest(Stats::VERSIONS),
+    };
+
+    const fn latest(versions: &[u32]) -> u32 {
+        versions[versions.len() - 1]
+    }
+}
+
+/// Migrates one block, prefixing the fields it lost with the block name.
+fn migrate_block<B: GdBlock>(b: &mut B, to: u32, lost: &mut Vec<String>) -> Result<()> {
+    let fields = b.migrate(to).map_err(|k| {
+        Error::new(
+            k,
+            Location {
+                block_seq: Some(B::SEQ),
+                path: vec![B::NAME.to_owned()],
+                ..Default::default()
+            },
+        )
+    })?;
+    lost.extend(fields.into_iter().map(|f| format!("{}.{f}", B::NAME)));
+    Ok(())
+}
+
+/// Location of `offset` in terms of the top-level block covering it, used to
+/// point at the part of the file a round trip failed in.
+fn location_at(spans: &[BlockSpan], offset: u64) -> Location {
+    let block = spans
+        .iter()
+        .find(|s| s.depth == 0 && (s.start..s.end).contains(&offset));
+
+    let name = match (block, spans.first()) {
+        (Some(b), _) => block_name(b.seq),
+        (None, Some(first)) if offset >= first.start => "end of file",
+        _ => "Header",
+    };
+
+    Location {
+        offset: Some(offset),
+        block_seq: block.map(|b| b.seq),
+        path: vec![name.to_owned()],
+    }
+}
+
+pub(crate) fn block_name(seq: u32) -> &'static str {
+    match seq {
+        1 => "Info",
+        2 => "Bio",
+        3 => "Inventory",
+        4 => "Stash",
+        5 => "RespawnList",
+        6 => "TeleportList",
+        7 => "MarkerList",
+        8 => "SkillList",
+        10 => "Crucible",
+        12 => "NoteList",
+        13 => "FactionList",
+        14 => "UI",
+        15 => "TutorialPages",
+        16 => "Stats",
+        17 => "ShrineList",
+        _ => "unknown block",
+    }
 }
 
 #[derive(Default, Debug, Display, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Sex {
     #[default]
     Female,
@@ -302,52 +699,88 @@ pub enum Sex {
 }
 
 impl TryFrom<u8> for Sex {
-    type Error = Error;
+    type Error = ErrorKind;
     fn try_from(t: u8) -> Result<Self, Self::Error> {
         match t {
             0 => Ok(Self::Female),
             1 => Ok(Self::Male),
-            _ => bail!(ParseError::ParseSexError(t)),
+            _ => Err(ErrorKind::InvalidValue {
+                what: "sex",
+                actual: t.into(),
+            }),
         }
     }
 }
 
 #[derive(Default, Debug, Display, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExpansionStatus {
     #[default]
-    Vanilla,
-    Crucible,
-    ForgottenGods,
-    AshesOfMalmouth,
+    Vanilla = 0,
+    Crucible = 2,
+    ForgottenGods = 3,
+    AshesOfMalmouth = 1,
 }
 
 impl TryFrom<u8> for ExpansionStatus {
-    type Error = Error;
+    type Error = ErrorKind;
     fn try_from(t: u8) -> Result<Self, Self::Error> {
         match t {
             0 => Ok(Self::Vanilla),
             1 => Ok(Self::AshesOfMalmouth),
             2 => Ok(Self::Crucible),
             3 => Ok(Self::ForgottenGods),
-            _ => bail!(ParseError::ParseExpansionStatusError(t)),
+            _ => Err(ErrorKind::InvalidValue {
+                what: "expansion status",
+                actual: t.into(),
+            }),
         }
     }
 }
 
 #[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Header {
     pub name: String,
     pub sex: Sex,
     pub hardcore: u8,
     pub level: u32,
     pub expansion_status: ExpansionStatus,
+    pub class_id: String,
     version: u32,
-    class_id: String,
-    #[default(_code = "vec![1, 2]")]
+    #[default(_code = "Self::VERSIONS.to_vec()")]
+    #[cfg_attr(feature = "serde", serde(skip))]
     supported_versions: Vec<u32>,
 }
 
 impl Header {
+    /// Known header versions, oldest first, like `GdBlock::VERSIONS`.
+    pub const VERSIONS: &'static [u32] = &[1, 2];
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Converts the header to version `to`, see `GdBlock::migrate`.
+    fn migrate(&mut self, to: u32) -> Result<Vec<&'static str>, ErrorKind> {
+        if !self.supported_versions.contains(&to) {
+            return Err(ErrorKind::UnsupportedVersion {
+                actual: to,
+                expected: self.supported_versions.clone(),
+            });
+        }
+
+        let mut lost = Vec::new();
+        // version 1 predates the expansions
+        if to < 2 && self.expansion_status != ExpansionStatus::default() {
+            lost.push("expansion_status");
+            self.expansion_status = ExpansionStatus::default();
+        }
+        self.version = to;
+        Ok(lost)
+    }
+
     pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
         f.write_int(self.version)?;
         f.write_wstring(&self.name)?;
@@ -363,18 +796,26 @@ impl Header {
     }
 
     fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in header")?;
-        self.name = f.read_wstring()?;
-        self.sex = f.read_byte()?.try_into()?;
-        self.class_id = f.read_string()?;
-        self.level = f.read_int()?;
-        self.hardcore = f.read_byte()?;
+        f.enter("Header");
+        self.version = f.read_version(&self.supported_versions)?;
+        self.name = f.field("name").read_wstring()?;
+        self.sex = f
+            .field("sex")
+            .read_byte()?
+            .try_into()
+            .map_err(|k| f.error(k))?;
+        self.class_id = f.field("class_id").read_string()?;
+        self.level = f.field("level").read_int()?;
+        self.hardcore = f.field("hardcore").read_byte()?;
 
         if self.version >= 2 {
-            self.expansion_status = f.read_byte()?.try_into()?;
+            self.expansion_status = f
+                .field("expansion_status")
+                .read_byte()?
+                .try_into()
+                .map_err(|k| f.error(k))?;
         }
+        f.leave();
 
         Ok(())
     }
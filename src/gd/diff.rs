@@ -0,0 +1,420 @@
+use crate::gd::char::Char;
+use crate::gd::item::Item;
+use crate::gd::skills::Skill;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// A single value of a character, compared by `Char::diff`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scalar {
+    Int(i64),
+    Float(f32),
+    Text(String),
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(n) => write!(f, "{n}"),
+            Self::Float(n) => write!(f, "{n}"),
+            Self::Text(s) => write!(f, "{s:?}"),
+        }
+    }
+}
+
+impl From<u32> for Scalar {
+    fn from(v: u32) -> Self {
+        Self::Int(v.into())
+    }
+}
+
+impl From<u8> for Scalar {
+    fn from(v: u8) -> Self {
+        Self::Int(v.into())
+    }
+}
+
+impl From<f32> for Scalar {
+    fn from(v: f32) -> Self {
+        Self::Float(v)
+    }
+}
+
+impl From<String> for Scalar {
+    fn from(v: String) -> Self {
+        Self::Text(v)
+    }
+}
+
+/// Where an item is kept.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Place {
+    Sack { sack: usize, x: u32, y: u32 },
+    Equipment { slot: usize },
+    Weapon { set: usize, slot: usize },
+    Stash { page: usize, x: f32, y: f32 },
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sack { sack, x, y } => write!(f, "sack {sack} at {x},{y}"),
+            Self::Equipment { slot } => write!(f, "equipment slot {slot}"),
+            Self::Weapon { set, slot } => write!(f, "weapon set {} slot {slot}", set + 1),
+            Self::Stash { page, x, y } => write!(f, "stash page {page} at {x},{y}"),
+        }
+    }
+}
+
+/// One difference between two versions of a character.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A plain value, e.g. `Stats.kills` or `Header.name`.
+    Value {
+        field: &'static str,
+        old: Scalar,
+        new: Scalar,
+    },
+    SkillAdded {
+        name: String,
+        level: u32,
+    },
+    SkillRemoved {
+        name: String,
+        level: u32,
+    },
+    SkillLevel {
+        name: String,
+        old: u32,
+        new: u32,
+    },
+    /// Any other value of a skill, e.g. `enabled` or `auto_cast_skill`.
+    SkillField {
+        name: String,
+        field: &'static str,
+        old: Scalar,
+        new: Scalar,
+    },
+    /// Reputation with the faction at `index`, in save order.
+    Faction {
+        index: usize,
+        old: f32,
+        new: f32,
+    },
+    ItemAdded {
+        item: String,
+        to: Place,
+    },
+    ItemRemoved {
+        item: String,
+        from: Place,
+    },
+    ItemMoved {
+        item: String,
+        from: Place,
+        to: Place,
+    },
+    /// The item in the same place, with another seed, affix, stack count...
+    ItemChanged {
+        item: String,
+        at: Place,
+        field: &'static str,
+        old: Scalar,
+        new: Scalar,
+    },
+    /// A block that differs in a way none of the other changes show, named
+    /// like its type, e.g. `UI` or `RespawnList`.
+    Block {
+        block: &'static str,
+    },
+}
+
+impl fmt::Display for Change {
+    /// One line per change, e.g. `Stats.kills: 2918 -> 3001 (+83)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value { field, old, new } => {
+                write!(f, "{field}: {old} -> {new}")?;
+                match (old, new) {
+                    (Scalar::Int(o), Scalar::Int(n)) => write!(f, " ({:+})", n - o),
+                    (Scalar::Float(o), Scalar::Float(n)) => write!(f, " ({:+})", n - o),
+                    _ => Ok(()),
+                }
+            }
+            Self::SkillAdded { name, level } => write!(f, "+ skill {name} (level {level})"),
+            Self::SkillRemoved { name, level } => write!(f, "- skill {name} (level {level})"),
+            Self::SkillLevel { name, old, new } => {
+                write!(f, "skill {name}: level {old} -> {new}")
+            }
+            Self::Faction { index, old, new } => {
+                write!(f, "faction {index}: {old} -> {new} ({:+})", new - old)
+            }
+            Self::ItemAdded { item, to } => write!(f, "+ item {item} in {to}"),
+            Self::ItemRemoved { item, from } => write!(f, "- item {item} from {from}"),
+            Self::ItemMoved { item, from, to } => {
+                write!(f, "item {item} moved from {from} to {to}")
+            }
+            Self::SkillField {
+                name,
+                field,
+                old,
+                new,
+            } => write!(f, "skill {name}: {field} {old} -> {new}"),
+            Self::ItemChanged {
+                item,
+                at,
+                field,
+                old,
+                new,
+            } => write!(f, "item {item} in {at}: {field} {old} -> {new}"),
+            Self::Block { block } => write!(f, "{block} changed"),
+        }
+    }
+}
+
+impl Char {
+    /// Everything that differs from `self` in `other`, e.g. an older and a
+    /// newer save of the same character. Values come first in save order,
+    /// then skills, factions and items. Blocks that differ only in parts
+    /// without a change of their own, like the UI or the discovered
+    /// shrines, are listed last as [`Change::Block`].
+    pub fn diff(&self, other: &Char) -> Vec<Change> {
+        let mut changes = Vec::new();
+        diff_values(&self.scalars(), &other.scalars(), &mut changes);
+        diff_skills(self, other, &mut changes);
+        diff_factions(self, other, &mut changes);
+        diff_items(&self.items(), &other.items(), &mut changes);
+
+        let shown: Vec<&str> = changes.iter().flat_map(Change::blocks).collect();
+        for (block, same) in self.blocks(other) {
+            if !same && !shown.contains(&block) {
+                changes.push(Change::Block { block });
+            }
+        }
+        changes
+    }
+
+    /// Whether each block is the same in `other`, in save order.
+    fn blocks(&self, other: &Char) -> Vec<(&'static str, bool)> {
+        vec![
+            ("Header", self.header == other.header),
+            ("Stats", self.stats == other.stats),
+            ("Bio", self.bio == other.bio),
+            (
+                "Char",
+                self.version == other.version && self.uid == other.uid,
+            ),
+            ("Info", self.info == other.info),
+            ("Inventory", self.inventory == other.inventory),
+            ("Stash", self.stash == other.stash),
+            ("RespawnList", self.respawns == other.respawns),
+            ("TeleportList", self.teleports == other.teleports),
+            ("MarkerList", self.markers == other.markers),
+            ("ShrineList", self.shrines == other.shrines),
+            ("SkillList", self.skills == other.skills),
+            ("NoteList", self.notes == other.notes),
+            ("FactionList", self.factions == other.factions),
+            ("UI", self.ui == other.ui),
+            ("TutorialPages", self.tutorials == other.tutorials),
+            ("Crucible", self.crucible == other.crucible),
+            ("Opaque", self.opaque_blocks() == other.opaque_blocks()),
+        ]
+    }
+
+    fn scalars(&self) -> Vec<(&'static str, Scalar)> {
+        let h = &self.header;
+        let mut v: Vec<(&'static str, Scalar)> = vec![
+            ("Header.name", h.name.clone().into()),
+            ("Header.sex", h.sex.to_string().into()),
+            ("Header.class_id", h.class_id.clone().into()),
+            ("Header.level", h.level.into()),
+            ("Header.hardcore", h.hardcore.into()),
+            (
+                "Header.expansion_status",
+                h.expansion_status.to_string().into(),
+            ),
+            ("Char.version", self.version.into()),
+            ("Info.money", self.info.money.into()),
+            (
+                "Info.greatest_difficulty",
+                self.info.greatest_difficulty.to_string().into(),
+            ),
+            (
+                "Info.greatest_crucible_difficulty",
+                self.info.greatest_crucible_difficulty.to_string().into(),
+            ),
+        ];
+        v.extend(self.info.scalars());
+        v.extend(self.bio.scalars());
+        v.extend([
+            (
+                "SkillList.skill_reclamation_points_used",
+                self.skills.skill_reclamation_points_used.into(),
+            ),
+            (
+                "SkillList.devotion_reclamation_points_used",
+                self.skills.devotion_reclamation_points_used.into(),
+            ),
+        ]);
+        v.extend(self.stats.scalars());
+        v
+    }
+
+    pub(crate) fn items(&self) -> Vec<(&Item, Place)> {
+        let mut items = self.inventory.items();
+        items.extend(self.stash.items());
+        items
+    }
+}
+
+impl Change {
+    /// Blocks the change was found in, by their type name.
+    fn blocks(&self) -> Vec<&'static str> {
+        let place = |p: &Place| match p {
+            Place::Stash { .. } => "Stash",
+            _ => "Inventory",
+        };
+        match self {
+            Self::Value { field, .. } => vec![field.split('.').next().unwrap_or(field)],
+            Self::SkillAdded { .. }
+            | Self::SkillRemoved { .. }
+            | Self::SkillLevel { .. }
+            | Self::SkillField { .. } => vec!["SkillList"],
+            Self::Faction { .. } => vec!["FactionList"],
+            Self::ItemAdded { to: p, .. }
+            | Self::ItemRemoved { from: p, .. }
+            | Self::ItemChanged { at: p, .. } => vec![place(p)],
+            Self::ItemMoved { from, to, .. } => vec![place(from), place(to)],
+            Self::Block { block } => vec![block],
+        }
+    }
+}
+
+fn diff_values(
+    old: &[(&'static str, Scalar)],
+    new: &[(&'static str, Scalar)],
+    changes: &mut Vec<Change>,
+) {
+    for ((field, o), (_, n)) in old.iter().zip(new.iter()) {
+        if o != n {
+            changes.push(Change::Value {
+                field,
+                old: o.clone(),
+                new: n.clone(),
+            });
+        }
+    }
+}
+
+fn diff_skills(old: &Char, new: &Char, changes: &mut Vec<Change>) {
+    fn by_name(c: &Char) -> HashMap<&str, &Skill> {
+        c.skills
+            .skills
+            .iter()
+            .map(|s| (s.name.as_str(), s))
+            .collect()
+    }
+    let (old_skills, new_skills) = (by_name(old), by_name(new));
+
+    for s in new.skills.skills.iter() {
+        let Some(o) = old_skills.get(s.name.as_str()) else {
+            changes.push(Change::SkillAdded {
+                name: s.name.clone(),
+                level: s.level,
+            });
+            continue;
+        };
+        if o.level != s.level {
+            changes.push(Change::SkillLevel {
+                name: s.name.clone(),
+                old: o.level,
+                new: s.level,
+            });
+        }
+        for ((field, o), (_, n)) in o.scalars().into_iter().zip(s.scalars()) {
+            if o != n {
+                changes.push(Change::SkillField {
+                    name: s.name.clone(),
+                    field,
+                    old: o,
+                    new: n,
+                });
+            }
+        }
+    }
+    for s in old.skills.skills.iter() {
+        if !new_skills.contains_key(s.name.as_str()) {
+            changes.push(Change::SkillRemoved {
+                name: s.name.clone(),
+                level: s.level,
+            });
+        }
+    }
+}
+
+fn diff_factions(old: &Char, new: &Char, changes: &mut Vec<Change>) {
+    let (old, new) = (&old.factions.factions, &new.factions.factions);
+    for index in 0..old.len().max(new.len()) {
+        let value = |f: &[crate::gd::misc::Faction]| f.get(index).map_or(0.0, |f| f.value);
+        let (o, n) = (value(old), value(new));
+        if o != n {
+            changes.push(Change::Faction {
+                index,
+                old: o,
+                new: n,
+            });
+        }
+    }
+}
+
+/// Pairs identical items: the ones found in another place have moved, the
+/// ones without a pair were added or removed, unless the same record is still
+/// in the same place: then it has changed, like a stack that grew.
+fn diff_items(old: &[(&Item, Place)], new: &[(&Item, Place)], changes: &mut Vec<Change>) {
+    let mut gone: Vec<Option<&(&Item, Place)>> =
+        old.iter().filter(|o| !new.contains(o)).map(Some).collect();
+
+    let mut added = Vec::new();
+    for n in new.iter().filter(|n| !old.contains(n)) {
+        if let Some(o) = gone.iter_mut().find(|o| o.is_some_and(|o| o.0 == n.0)) {
+            let (item, from) = o.take().unwrap();
+            changes.push(Change::ItemMoved {
+                item: item.id().to_owned(),
+                from: *from,
+                to: n.1,
+            });
+        } else if let Some(o) = gone
+            .iter_mut()
+            .find(|o| o.is_some_and(|o| o.1 == n.1 && o.0.id() == n.0.id()))
+        {
+            let (item, at) = o.take().unwrap();
+            for ((field, o), (_, n)) in item.scalars().into_iter().zip(n.0.scalars()) {
+                if o != n {
+                    changes.push(Change::ItemChanged {
+                        item: item.id().to_owned(),
+                        at: *at,
+                        field,
+                        old: o,
+                        new: n,
+                    });
+                }
+            }
+        } else {
+            added.push(Change::ItemAdded {
+                item: n.0.id().to_owned(),
+                to: n.1,
+            });
+        }
+    }
+
+    changes.extend(added);
+    changes.extend(
+        gone.into_iter()
+            .flatten()
+            .map(|(item, from)| Change::ItemRemoved {
+                item: item.id().to_owned(),
+                from: *from,
+            }),
+    );
+}
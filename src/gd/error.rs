@@ -0,0 +1,127 @@
+use std::fmt;
+use std::io;
+
+use thiserror::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Where in a save file an error happened. Errors that don't come from
+/// reading the file, like I/O errors, leave all of it unknown.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    /// Byte offset from the start of the file, if the error is tied to one.
+    pub offset: Option<u64>,
+    /// Sequence number of the top-level block being read, if any.
+    pub block_seq: Option<u32>,
+    /// Names of the nested structures being read, outermost first,
+    /// e.g. `["Inventory", "sack 2", "item 5"]`.
+    pub path: Vec<String>,
+}
+
+impl fmt::Display for Location {
+    /// Only the known parts, each with a leading space, e.g.
+    /// ` at offset 0x01a4 in Inventory > sack 2 (block 3)`, so an unknown
+    /// location prints nothing.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset:#06x}")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path.join(" > "))?;
+        }
+        if let Some(seq) = self.block_seq {
+            write!(f, " (block {seq})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned by everything that reads or writes saves.
+#[derive(Error, Debug)]
+#[error("{kind}{at}")]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub at: Location,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, at: Location) -> Self {
+        Self { kind, at }
+    }
+
+    pub fn offset(&self) -> Option<u64> {
+        self.at.offset
+    }
+
+    pub fn block_seq(&self) -> Option<u32> {
+        self.at.block_seq
+    }
+
+    /// Block path in the `Inventory > sack 2 > item 5` form.
+    pub fn path(&self) -> String {
+        self.at.path.join(" > ")
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::new(ErrorKind::Io(e), Location::default())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid file magic: {actual:#010x}, expected {expected:#010x}")]
+    InvalidMagic { actual: u32, expected: u32 },
+    #[error("Unsupported version: {actual}, expected {expected:?}")]
+    UnsupportedVersion { actual: u32, expected: Vec<u32> },
+    #[error("Incorrect block end position: {actual}, expected {expected}")]
+    BlockEndPosition { actual: u64, expected: u64 },
+    #[error("Failed to validate block ending: {actual}, expected {expected}")]
+    BlockEnding { actual: u32, expected: u32 },
+    #[error("Failed to validate block order: {actual}, expected {expected}")]
+    BlockOrder { actual: u32, expected: u32 },
+    #[error("{what} {actual} exceeds the limit {limit}")]
+    LimitExceeded {
+        what: &'static str,
+        actual: u32,
+        limit: u32,
+    },
+    #[error(
+        "Not enough data for {what}: needs at least {actual} bytes, {available} left in the block"
+    )]
+    PastBlockEnd {
+        what: &'static str,
+        actual: u64,
+        available: u64,
+    },
+    #[error("Block is too large to write: {0} bytes")]
+    BlockTooLarge(u64),
+    #[error("Unexpected {what}: {actual}, expected {expected}")]
+    UnexpectedValue {
+        what: &'static str,
+        actual: u32,
+        expected: u32,
+    },
+    #[error("Invalid {what}: {actual}")]
+    InvalidValue { what: &'static str, actual: u32 },
+    #[error("Failed to validate write amount: {actual}, expected {expected}")]
+    WriteAmount { actual: usize, expected: usize },
+    #[error("Unexpected end of file while reading {0} bytes")]
+    UnexpectedEof(usize),
+    #[error("Invalid UTF-16 string: unpaired surrogate {0:#06x}")]
+    InvalidUtf16(u16),
+    #[error("Character {0:?} doesn't fit in a Latin-1 string")]
+    NotLatin1(char),
+    #[error("Opaque block {0} was read with a different key and can't be written back")]
+    OpaqueBlockKeyMismatch(u32),
+    #[error("Opaque block {0} has an unknown layout and can't be migrated")]
+    OpaqueBlockMigration(u32),
+    #[error("Round trip differs: expected {expected:?}, got {actual:?}")]
+    RoundtripMismatch {
+        expected: Option<u8>,
+        actual: Option<u8>,
+    },
+}
@@ -0,0 +1,54 @@
+use crate::gd::char::Char;
+use crate::gd::error::Error;
+use crate::gd::gd_file::{Field, GDBuf};
+
+use std::fmt;
+
+/// Annotated dump of a save file, see `explain`.
+#[derive(Debug)]
+pub struct Explanation {
+    /// Every value decoded before parsing stopped, in file order.
+    pub fields: Vec<Field>,
+    /// Why parsing stopped early, if it did.
+    pub error: Option<Error>,
+    /// Size of the whole file, to tell how much of it was left unread.
+    pub len: u64,
+}
+
+impl fmt::Display for Explanation {
+    /// One field per line, e.g. `0x01a4 Bio.physique f32 258.0 [..]`,
+    /// followed by how much is left unread if reading failed. The error
+    /// itself is left to the caller.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for field in self.fields.iter() {
+            writeln!(f, "{field}")?;
+        }
+
+        if self.error.is_some() {
+            let read = self
+                .fields
+                .last()
+                .map_or(0, |l| l.offset + l.raw.len() as u64);
+            writeln!(f, "{} of {} bytes left unread", self.len - read, self.len)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Walks a save with the regular reader and records every value it decodes:
+/// offset, raw bytes, decrypted value and the name of the field it goes to.
+/// Parsing errors don't fail the dump; they end it, so the last lines show
+/// what was read right before things went wrong.
+pub fn explain(data: &[u8]) -> Explanation {
+    let mut f = GDBuf::new(data);
+    f.with_trace();
+
+    let error = Char::new().read_buf(&mut f).err();
+
+    Explanation {
+        fields: f.trace().to_vec(),
+        error,
+        len: data.len() as u64,
+    }
+}
@@ -1,12 +1,159 @@
-use anyhow::{bail, Ok, Result};
-use std::fs::File;
+use crate::gd::error::{Error, ErrorKind, Location, Result};
+
+use smart_default::SmartDefault;
 use std::io::{prelude::*, SeekFrom};
-use std::vec;
-use thiserror::Error;
 
 pub trait ReadWrite {
+    /// Name of a single element, used in error paths like `sack 2`. Empty
+    /// for plain values, whose elements are named after the list instead.
+    const NAME: &'static str;
+    /// Fewest bytes a value takes in the file, so that a count of them can
+    /// be checked against the bytes left before anything is allocated.
+    const MIN_SIZE: usize = 1;
+
     fn read(&mut self, f: &mut impl GDReader) -> Result<()>;
     fn write(&self, f: &mut impl GDWriter) -> Result<()>;
+
+    /// Reads `self` as the field `name` of the structure being read.
+    fn read_named(&mut self, name: &'static str, f: &mut impl GDReader) -> Result<()> {
+        f.enter(name);
+        self.read(f)?;
+        f.leave();
+        Ok(())
+    }
+}
+
+/// A top-level block of a save, usually implemented with
+/// `#[derive(GdBlock)]`.
+pub trait GdBlock: ReadWrite {
+    /// Sequence number the block starts with.
+    const SEQ: u32;
+    /// Block versions the structure knows how to read and write.
+    const VERSIONS: &'static [u32];
+
+    /// Version of the layout the block is currently in.
+    fn version(&self) -> u32;
+
+    /// Converts the block to the layout of version `to`. Fields that layout
+    /// doesn't have are reset to their defaults, and the names of those that
+    /// held data are returned. Fields new in `to` keep their defaults.
+    fn migrate(&mut self, to: u32) -> Result<Vec<&'static str>, ErrorKind>;
+
+    /// Fails unless the block can be written as version `to`.
+    fn check_version(to: u32) -> Result<(), ErrorKind> {
+        if !Self::VERSIONS.contains(&to) {
+            return Err(ErrorKind::UnsupportedVersion {
+                actual: to,
+                expected: Self::VERSIONS.to_vec(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Resets a field that doesn't exist in the layout being migrated to,
+/// remembering its name in `lost` if it wasn't empty.
+pub fn drop_field<T: Default + PartialEq>(
+    v: &mut T,
+    name: &'static str,
+    lost: &mut Vec<&'static str>,
+) {
+    if *v != T::default() {
+        lost.push(name);
+    }
+    *v = T::default();
+}
+
+macro_rules! read_write_value {
+    ($t:ty, $read:ident, $write:ident) => {
+        impl ReadWrite for $t {
+            const NAME: &'static str = "";
+            const MIN_SIZE: usize = std::mem::size_of::<$t>();
+
+            fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+                *self = f.$read()?;
+                Ok(())
+            }
+
+            fn write(&self, f: &mut impl GDWriter) -> Result<()> {
+                f.$write(*self)
+            }
+
+            fn read_named(&mut self, name: &'static str, f: &mut impl GDReader) -> Result<()> {
+                f.field(name);
+                self.read(f)
+            }
+        }
+    };
+}
+
+read_write_value!(u8, read_byte, write_byte);
+read_write_value!(u32, read_int, write_int);
+read_write_value!(f32, read_float, write_float);
+
+impl ReadWrite for String {
+    const NAME: &'static str = "";
+    const MIN_SIZE: usize = 4;
+
+    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+        *self = f.read_string()?;
+        Ok(())
+    }
+
+    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
+        f.write_string(self)
+    }
+
+    fn read_named(&mut self, name: &'static str, f: &mut impl GDReader) -> Result<()> {
+        f.field(name);
+        self.read(f)
+    }
+}
+
+/// Lists are stored as a count followed by the elements.
+impl<T: ReadWrite + Default> ReadWrite for Vec<T> {
+    const NAME: &'static str = T::NAME;
+    const MIN_SIZE: usize = 4;
+
+    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+        *self = f.read_vec()?;
+        Ok(())
+    }
+
+    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
+        f.write_vec(self)
+    }
+
+    fn read_named(&mut self, name: &'static str, f: &mut impl GDReader) -> Result<()> {
+        f.field(name);
+        self.read(f)
+    }
+}
+
+/// Fixed-size arrays are stored as their elements, without a count.
+impl<T: ReadWrite, const N: usize> ReadWrite for [T; N] {
+    const NAME: &'static str = T::NAME;
+    const MIN_SIZE: usize = N * T::MIN_SIZE;
+
+    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+        self.read_named(T::NAME, f)
+    }
+
+    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
+        for v in self.iter() {
+            v.write(f)?;
+        }
+        Ok(())
+    }
+
+    fn read_named(&mut self, name: &'static str, f: &mut impl GDReader) -> Result<()> {
+        for (i, v) in self.iter_mut().enumerate() {
+            f.enter_index(name, i);
+            v.read(f)?;
+            f.leave();
+        }
+        Ok(())
+    }
 }
 
 pub trait GDReader {
@@ -20,167 +167,591 @@ pub trait GDReader {
     fn read_float(&mut self) -> Result<f32>;
     fn read_version(&mut self, supported_versions: &[u32]) -> Result<u32>;
     fn read_int(&mut self) -> Result<u32>;
+    /// Reads the number of elements that follow, checked against the
+    /// reader's `Limits` and the bytes left in the current block for
+    /// elements of at least `size` bytes.
+    fn read_count(&mut self, c: Count, size: usize) -> Result<usize>;
     fn next_int(&mut self) -> Result<u32>;
     fn read_block_start(&mut self, b: &mut Block, n: u32) -> Result<()>;
     fn read_block_end(&mut self, b: &mut Block) -> Result<()>;
+    /// Enters a named structure; shows up in the location of errors.
+    fn enter(&mut self, name: &'static str);
+    /// Enters the `i`-th element of a repeated structure.
+    fn enter_index(&mut self, name: &'static str, i: usize);
+    fn leave(&mut self);
+    /// Names the next value read; shows up in traces as `Bio.physique`.
+    fn field(&mut self, name: &'static str) -> &mut Self;
+    /// Builds an error located at the current read position.
+    fn error(&self, kind: ErrorKind) -> Error;
 }
 
 pub trait GDWriter {
     fn write_vec<T: ReadWrite + Default>(&mut self, items: &[T]) -> Result<()>;
     fn write_arr<T: ReadWrite + Default>(&mut self, items: &[T]) -> Result<()>;
     fn write_byte(&mut self, v: u8) -> Result<()>;
+    fn write_key(&mut self, seed: u32) -> Result<()>;
     fn write_string(&mut self, s: &str) -> Result<()>;
     fn write_wstring(&mut self, s: &str) -> Result<()>;
     fn write_float(&mut self, v: f32) -> Result<()>;
     fn write_int(&mut self, v: u32) -> Result<()>;
+    fn write_next_int(&mut self, v: u32) -> Result<()>;
     fn write_block_start(&mut self, b: &mut Block, n: u32) -> Result<()>;
     fn write_block_end(&mut self, b: &mut Block) -> Result<()>;
 }
 
-#[derive(Error, Debug)]
-enum FileError {
-    #[error("Unsupported version: {0}, expected {1:?}")]
-    UnsupportedVersion(u32, Vec<u32>),
-    #[error("Incorrect block end position: {0}, expected {1}")]
-    IncorrectBlockEndPosition(u64, u64),
-    #[error("Failed to validate block ending: {0}, expected {1}")]
-    FailedToValidateBlockEnding(u32, u32),
-    #[error("Failed to validate block order: {0}, expected {1}")]
-    FailedToValidateBlockOrder(u32, u32),
-    #[error("Failed to validate write amount: {0}, expected {1}")]
-    FailedToValidateWriteAmount(usize, u32),
-}
-
 #[derive(Default, Debug)]
 pub struct Block {
     len: u32,
     end: u64,
+    key: u32,
+}
+
+/// Upper bounds for lengths read from a save. Anything above them is
+/// treated as corruption instead of being allocated or looped over.
+#[derive(SmartDefault, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Characters in a single string.
+    #[default = 4096]
+    pub string_len: u32,
+    /// Elements in any other list, e.g. items in a sack or notes.
+    #[default = 16384]
+    pub items: u32,
+    #[default = 64]
+    pub sacks: u32,
+    #[default = 64]
+    pub stash_pages: u32,
+}
+
+/// What a length prefix counts, to pick the matching limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+    Items,
+    Sacks,
+    StashPages,
+}
+
+impl Count {
+    fn limit(self, l: &Limits) -> u32 {
+        match self {
+            Count::Items => l.items,
+            Count::Sacks => l.sacks,
+            Count::StashPages => l.stash_pages,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Count::Items => "items",
+            Count::Sacks => "sacks",
+            Count::StashPages => "stash pages",
+        }
+    }
+}
+
+/// Location of a block in the decoded file: `start` points at its sequence
+/// number and `end` just past its end marker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockSpan {
+    pub seq: u32,
+    pub start: u64,
+    pub end: u64,
+    pub depth: usize,
 }
 
-pub struct GDFile {
-    f: File,
+/// A block kept as raw bytes because its version isn't understood.
+///
+/// The bytes are still encrypted with the key stream of the source file.
+/// Without knowing the field layout they can't be re-encrypted, so writing
+/// them back requires the writer to be in exactly the same key state the
+/// reader was in. That always holds for plain text saves (seed 0); for
+/// encrypted saves it holds as long as nothing before the block changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpaqueBlock {
+    pub seq: u32,
+    pub version: u32,
+    raw: Vec<u8>,
+    seed: u32,
+    key_in: u32,
+    key_out: u32,
+}
+
+impl OpaqueBlock {
+    /// Size of the block on disk, including its header and end marker.
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+}
+
+/// Reader state to come back to when a block has to be re-read opaquely.
+pub struct Mark {
+    pos: usize,
+    key: u32,
+    blocks: usize,
+    scopes: usize,
+    spans: usize,
+    trace: usize,
+}
+
+/// A decoded value as recorded by a traced reader.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Key(u32),
+    Magic(u32),
+    U8(u8),
+    U32(u32),
+    F32(f32),
+    Version(u32),
+    String(String),
+    WString(String),
+    BlockStart { seq: u32, len: u32 },
+    BlockEnd(u32),
+    NextInt(u32),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Key(seed) => write!(f, "seed {seed:#010x}"),
+            Value::Magic(v) => write!(f, "u32 {v:#010x}"),
+            Value::U8(v) => write!(f, "u8 {v}"),
+            Value::U32(v) => write!(f, "u32 {v}"),
+            Value::F32(v) => write!(f, "f32 {v:?}"),
+            Value::Version(v) => write!(f, "version {v}"),
+            Value::String(v) => write!(f, "string {v:?}"),
+            Value::WString(v) => write!(f, "wstring {v:?}"),
+            Value::BlockStart { seq, len } => write!(f, "block {seq} start, len {len}"),
+            Value::BlockEnd(seq) => write!(f, "block {seq} end"),
+            Value::NextInt(v) => write!(f, "int {v}"),
+        }
+    }
+}
+
+/// One value read from a save: where it is, how it's stored and what it
+/// decodes to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub offset: u64,
+    /// Bytes as found in the file, i.e. still encrypted.
+    pub raw: Vec<u8>,
+    /// Dotted path of the value, e.g. `Inventory.sack[0].item[1].seed`.
+    pub name: String,
+    pub value: Value,
+}
+
+impl std::fmt::Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const MAX_RAW: usize = 16;
+
+        write!(f, "{:#06x} {} {} [", self.offset, self.name, self.value)?;
+        for (i, b) in self.raw.iter().take(MAX_RAW).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{b:02x}")?;
+        }
+        if self.raw.len() > MAX_RAW {
+            write!(f, " ..")?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Scope {
+    name: &'static str,
+    index: Option<usize>,
+}
+
+/// Expands a seed into the table used to roll the XOR key forward after
+/// every encrypted byte.
+fn key_table(mut k: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    for t in table.iter_mut() {
+        k = k.rotate_right(1);
+        k = k.wrapping_mul(39916801_u32);
+        *t = k;
+    }
+    table
+}
+
+/// Decodes a save file that has already been loaded into memory. All reads
+/// are served from the slice, so decoding never touches the underlying file.
+pub struct GDBuf<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    /// Sequence number and end position of every block being read.
+    blocks: Vec<(u32, usize)>,
+    scopes: Vec<Scope>,
+    spans: Vec<BlockSpan>,
+    seed: u32,
     key: u32,
     table: [u32; 256],
     supported_versions: Vec<u32>,
+    limits: Limits,
+    field: Option<&'static str>,
+    trace: Option<Vec<Field>>,
 }
 
-impl GDFile {
-    pub fn new(f: File) -> Self {
+impl<'a> GDBuf<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
         Self {
-            f,
+            buf,
+            pos: 0,
+            blocks: Vec::new(),
+            scopes: Vec::new(),
+            spans: Vec::new(),
+            seed: 0,
             key: 0,
             table: [0; 256],
             supported_versions: vec![0x58434447],
+            limits: Limits::default(),
+            field: None,
+            trace: None,
         }
     }
 
-    fn update_key(&mut self, val: Vec<u8>) {
-        for i in val {
-            self.key ^= self.table[i as usize];
+    pub fn with_limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Records every value read from now on, see `trace`.
+    pub fn with_trace(&mut self) -> &mut Self {
+        self.trace = Some(Vec::new());
+        self
+    }
+
+    /// Values read so far, in file order. Empty unless `with_trace` was
+    /// called.
+    pub fn trace(&self) -> &[Field] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    /// Seed the file was encrypted with, known after `validate`.
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn mark(&self) -> Mark {
+        Mark {
+            pos: self.pos,
+            key: self.key,
+            blocks: self.blocks.len(),
+            scopes: self.scopes.len(),
+            spans: self.spans.len(),
+            trace: self.trace().len(),
         }
     }
-}
 
-impl GDReader for GDFile {
-    fn read_vec<T: ReadWrite + Default>(&mut self) -> Result<Vec<T>> {
-        let n = self.read_int()? as usize;
-        self.read_arr(n)
+    /// Rewinds to `mark`, which must sit at the start of block `n`, and
+    /// reads the whole block back as raw bytes.
+    pub fn read_opaque_block(&mut self, mark: Mark, n: u32) -> Result<OpaqueBlock> {
+        self.pos = mark.pos;
+        self.key = mark.key;
+        self.blocks.truncate(mark.blocks);
+        self.scopes.truncate(mark.scopes);
+        self.spans.truncate(mark.spans);
+        if let Some(t) = self.trace.as_mut() {
+            t.truncate(mark.trace);
+        }
+
+        let mut b = Block::default();
+        self.read_block_start(&mut b, n)?;
+        let version = self.next_int()?;
+
+        let end = b.end as usize;
+        if end < self.pos || end > self.buf.len() {
+            return Err(self.error(ErrorKind::BlockEndPosition {
+                actual: self.buf.len() as u64,
+                expected: b.end,
+            }));
+        }
+        self.pos = end;
+
+        // the end marker decodes to zero, so its raw value is the key the
+        // block's contents left behind
+        let key_out = u32::from_le_bytes(self.take()?);
+        self.key = key_out;
+        self.blocks.truncate(mark.blocks);
+
+        Ok(OpaqueBlock {
+            seq: n,
+            version,
+            raw: self.buf[mark.pos..self.pos].to_vec(),
+            seed: self.seed,
+            key_in: mark.key,
+            key_out,
+        })
     }
 
-    fn read_arr<T: ReadWrite + Default>(&mut self, n: usize) -> Result<Vec<T>> {
+    /// Every block read so far, in file order.
+    pub fn spans(&self) -> &[BlockSpan] {
+        &self.spans
+    }
+
+    fn location(&self, offset: usize) -> Location {
+        Location {
+            offset: Some(offset as u64),
+            block_seq: self.blocks.first().map(|&(seq, _)| seq),
+            path: self.scope_names(|name, i| match i {
+                Some(i) => format!("{name} {i}"),
+                None => name.to_owned(),
+            }),
+        }
+    }
+
+    /// Formats every scope with `each`. Unnamed elements, such as the lists
+    /// in an array of lists, are appended to the scope they are in, e.g.
+    /// `tokens 2 1` or `tokens[2][1]`.
+    fn scope_names(&self, each: impl Fn(&str, Option<usize>) -> String) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for s in self.scopes.iter() {
+            match (s.name, names.last_mut()) {
+                ("", Some(last)) => *last += &each("", s.index),
+                _ => names.push(each(s.name, s.index)),
+            }
+        }
+        names
+    }
+
+    /// Adds the value that spans from `start` to the current position to the
+    /// trace, named after the current scopes and the pending field name.
+    fn record(&mut self, start: usize, value: impl FnOnce() -> Value) {
+        let field = self.field.take();
+        if self.trace.is_none() {
+            return;
+        }
+
+        let mut name = self.scope_names(|name, i| match i {
+            Some(i) => format!("{}[{i}]", name.replace(' ', "_")),
+            None => name.replace(' ', "_"),
+        });
+        name.extend(field.map(str::to_owned));
+
+        let field = Field {
+            offset: start as u64,
+            raw: self.buf[start..self.pos].to_vec(),
+            name: name.join("."),
+            value: value(),
+        };
+        if let Some(t) = self.trace.as_mut() {
+            t.push(field);
+        }
+    }
+
+    /// End of the innermost block being read, or of the file.
+    fn end(&self) -> usize {
+        self.blocks
+            .last()
+            .map_or(self.buf.len(), |&(_, end)| end.min(self.buf.len()))
+    }
+
+    /// Checks that `n` elements of at least `size` bytes each can still be
+    /// read from the current block.
+    fn check_fits(&self, start: usize, what: &'static str, n: u32, size: usize) -> Result<()> {
+        let available = self.end().saturating_sub(self.pos);
+        if n as usize > available / size {
+            return Err(self.error_at(
+                start,
+                ErrorKind::PastBlockEnd {
+                    what,
+                    actual: n as u64 * size as u64,
+                    available: available as u64,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_limit(&self, start: usize, what: &'static str, n: u32, limit: u32) -> Result<()> {
+        if n > limit {
+            return Err(self.error_at(
+                start,
+                ErrorKind::LimitExceeded {
+                    what,
+                    actual: n,
+                    limit,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    fn read_elements<T: ReadWrite + Default>(
+        &mut self,
+        n: usize,
+        each: &'static str,
+    ) -> Result<Vec<T>> {
         let mut items: Vec<T> = Vec::with_capacity(n);
-        for _ in 0..n {
+        for idx in 0..n {
+            self.enter_index(each, idx);
             let mut i = T::default();
             i.read(self)?;
             items.push(i);
+            self.leave();
         }
         Ok(items)
     }
 
+    fn decode_byte(&mut self) -> Result<u8> {
+        let buf = self.take::<1>()?;
+        let ret = buf[0] ^ self.key as u8;
+        self.update_key(&buf);
+
+        Ok(ret)
+    }
+
+    fn decode_int(&mut self) -> Result<u32> {
+        let buf = self.take::<4>()?;
+        let ret = u32::from_le_bytes(buf) ^ self.key;
+        self.update_key(&buf);
+
+        Ok(ret)
+    }
+
+    fn decode_next_int(&mut self) -> Result<u32> {
+        let buf = self.take::<4>()?;
+        Ok(u32::from_le_bytes(buf) ^ self.key)
+    }
+
+    fn error_at(&self, offset: usize, kind: ErrorKind) -> Error {
+        Error::new(kind, self.location(offset))
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let Some(bytes) = self.buf.get(self.pos..self.pos + N) else {
+            return Err(self.error(ErrorKind::UnexpectedEof(N)));
+        };
+        let mut out = [0; N];
+        out.copy_from_slice(bytes);
+        self.pos += N;
+        Ok(out)
+    }
+
+    fn update_key(&mut self, val: &[u8]) {
+        for &i in val {
+            self.key ^= self.table[i as usize];
+        }
+    }
+}
+
+impl GDReader for GDBuf<'_> {
+    fn read_vec<T: ReadWrite + Default>(&mut self) -> Result<Vec<T>> {
+        // plain values are named after the list, e.g. `notes 3`
+        let each = match (T::NAME, self.field) {
+            ("", Some(name)) => name,
+            _ => T::NAME,
+        };
+        let n = self.read_count(Count::Items, T::MIN_SIZE)?;
+        self.read_elements(n, each)
+    }
+
+    fn read_arr<T: ReadWrite + Default>(&mut self, n: usize) -> Result<Vec<T>> {
+        self.read_elements(n, T::NAME)
+    }
+
     fn validate(&mut self) -> Result<()> {
-        self.f.seek(SeekFrom::End(0))?;
-        let _end = self.f.stream_position()?;
-        self.f.seek(SeekFrom::Start(0))?;
+        self.pos = 0;
 
         self.read_key()?;
 
-        let ret = self.read_int()?;
+        let ret = self.decode_int()?;
+        self.field.get_or_insert("magic");
+        self.record(self.pos - 4, || Value::Magic(ret));
         if !self.supported_versions.contains(&ret) {
-            bail!(FileError::UnsupportedVersion(
-                ret,
-                self.supported_versions.to_vec()
+            return Err(self.error_at(
+                self.pos - 4,
+                ErrorKind::InvalidMagic {
+                    actual: ret,
+                    expected: self.supported_versions[0],
+                },
             ));
         };
         Ok(())
     }
 
     fn read_string(&mut self) -> Result<String> {
-        let len = self.read_int()?;
+        let start = self.pos;
+        let len = self.decode_int()?;
+        self.check_limit(start, "string length", len, self.limits.string_len)?;
+        self.check_fits(start, "string", len, 1)?;
 
         let mut s = String::new();
         s.reserve(len as usize);
 
         for _ in 0..len {
-            s.push(self.read_byte()?.into());
+            s.push(self.decode_byte()?.into());
         }
+        self.record(start, || Value::String(s.clone()));
         Ok(s)
     }
 
     fn read_wstring(&mut self) -> Result<String> {
-        let n = self.read_int()? as usize;
-
-        let mut s = String::new();
-        s.reserve(n);
+        let start = self.pos;
+        let n = self.decode_int()?;
+        self.check_limit(start, "string length", n, self.limits.string_len)?;
+        self.check_fits(start, "string", n, 2)?;
 
+        let mut units = Vec::with_capacity(n as usize);
         for _ in 0..n {
-            let mut c = self.read_byte()?;
-            c |= self.read_byte()?.wrapping_shl(8);
-            s.push(c.into());
+            let lo = self.decode_byte()?;
+            let hi = self.decode_byte()?;
+            units.push(u16::from_le_bytes([lo, hi]));
+        }
+
+        let mut s = String::with_capacity(units.len());
+        // offset of the code unit being decoded, for errors
+        let mut at = start + 4;
+        for c in char::decode_utf16(units) {
+            let c =
+                c.map_err(|e| self.error_at(at, ErrorKind::InvalidUtf16(e.unpaired_surrogate())))?;
+            s.push(c);
+            at += 2 * c.len_utf16();
         }
+        self.record(start, || Value::WString(s.clone()));
         Ok(s)
     }
 
     fn read_byte(&mut self) -> Result<u8> {
-        let mut buf: [u8; 1] = [0; 1];
-        self.f.read_exact(&mut buf)?;
-        let val = u8::from_ne_bytes(buf);
-        let ret = val ^ self.key as u8;
-        self.update_key(buf.to_vec());
-
+        let ret = self.decode_byte()?;
+        self.record(self.pos - 1, || Value::U8(ret));
         Ok(ret)
     }
 
     fn read_key(&mut self) -> Result<()> {
-        let mut buf: [u8; 4] = [0; 4];
-        self.f.read_exact(&mut buf)?;
-        let mut k = u32::from_ne_bytes(buf);
-        k ^= 1431655765_u32;
+        let k = u32::from_le_bytes(self.take()?) ^ 1431655765_u32;
+        self.seed = k;
         self.key = k;
-
-        for i in 0..256 {
-            k = k >> 1 | k << 31;
-            k = k.wrapping_mul(39916801_u32);
-            self.table[i] = k;
-        }
+        self.table = key_table(k);
+        self.field.get_or_insert("key");
+        self.record(self.pos - 4, || Value::Key(k));
 
         Ok(())
     }
 
     fn read_float(&mut self) -> Result<f32> {
-        Ok(f32::from_bits(self.read_int()?))
+        let ret = f32::from_bits(self.decode_int()?);
+        self.record(self.pos - 4, || Value::F32(ret));
+        Ok(ret)
     }
 
     fn read_version(&mut self, supported_versions: &[u32]) -> Result<u32> {
-        let mut buf: [u8; 4] = [0; 4];
-        self.f.read_exact(&mut buf)?;
-        let val = u32::from_ne_bytes(buf);
-        let ret = val ^ self.key;
-        self.update_key(buf.to_vec());
+        let ret = self.decode_int()?;
+        self.field.get_or_insert("version");
+        self.record(self.pos - 4, || Value::Version(ret));
 
         if !supported_versions.contains(&ret) {
-            bail!(FileError::UnsupportedVersion(
-                ret,
-                supported_versions.to_vec()
+            return Err(self.error_at(
+                self.pos - 4,
+                ErrorKind::UnsupportedVersion {
+                    actual: ret,
+                    expected: supported_versions.to_vec(),
+                },
             ));
         }
 
@@ -188,51 +759,186 @@ impl GDReader for GDFile {
     }
 
     fn read_int(&mut self) -> Result<u32> {
-        let mut buf: [u8; 4] = [0; 4];
-        self.f.read_exact(&mut buf)?;
-        let val = u32::from_ne_bytes(buf);
-        let ret = val ^ self.key;
-        self.update_key(buf.to_vec());
-
+        let ret = self.decode_int()?;
+        self.record(self.pos - 4, || Value::U32(ret));
         Ok(ret)
     }
 
+    fn read_count(&mut self, c: Count, size: usize) -> Result<usize> {
+        let start = self.pos;
+        let n = self.read_int()?;
+        self.check_limit(start, c.name(), n, c.limit(&self.limits))?;
+        self.check_fits(start, c.name(), n, size.max(1))?;
+        Ok(n as usize)
+    }
+
     fn next_int(&mut self) -> Result<u32> {
-        let mut buf: [u8; 4] = [0; 4];
-        self.f.read_exact(&mut buf)?;
-        let val = u32::from_ne_bytes(buf);
-        Ok(val ^ self.key)
+        let ret = self.decode_next_int()?;
+        self.record(self.pos - 4, || Value::NextInt(ret));
+        Ok(ret)
     }
 
     fn read_block_start(&mut self, b: &mut Block, n: u32) -> Result<()> {
-        let ret = self.read_int()?;
-        b.len = self.next_int()?;
-        b.end = self.f.stream_position()? + b.len as u64;
+        let start = self.pos;
+        let ret = self.decode_int()?;
+        b.len = self.decode_next_int()?;
+        b.end = self.pos as u64 + b.len as u64;
+        self.record(start, || Value::BlockStart {
+            seq: ret,
+            len: b.len,
+        });
 
         if ret != n {
-            bail!(FileError::FailedToValidateBlockOrder(ret, n))
+            return Err(self.error_at(
+                start,
+                ErrorKind::BlockOrder {
+                    actual: ret,
+                    expected: n,
+                },
+            ));
+        }
+
+        // the block and its end marker have to fit in the enclosing one
+        let available = self.end().saturating_sub(self.pos);
+        if b.len as usize + 4 > available {
+            return Err(self.error_at(
+                start,
+                ErrorKind::PastBlockEnd {
+                    what: "block",
+                    actual: b.len as u64 + 4,
+                    available: available as u64,
+                },
+            ));
         }
+
+        self.spans.push(BlockSpan {
+            seq: n,
+            start: start as u64,
+            end: b.end + 4,
+            depth: self.blocks.len(),
+        });
+        self.blocks.push((n, b.end as usize));
         Ok(())
     }
 
     fn read_block_end(&mut self, b: &mut Block) -> Result<()> {
-        if b.end != self.f.stream_position()? {
-            bail!(FileError::IncorrectBlockEndPosition(
-                self.f.stream_position()?,
-                b.end
-            ));
+        if b.end != self.pos as u64 {
+            return Err(self.error(ErrorKind::BlockEndPosition {
+                actual: self.pos as u64,
+                expected: b.end,
+            }));
         }
 
-        let ni = self.next_int()?;
+        let ni = self.decode_next_int()?;
+        let seq = self.blocks.last().map_or(0, |&(seq, _)| seq);
+        self.record(self.pos - 4, || Value::BlockEnd(seq));
         if ni != 0 {
-            bail!(FileError::FailedToValidateBlockEnding(ni, 0));
+            return Err(self.error_at(
+                self.pos - 4,
+                ErrorKind::BlockEnding {
+                    actual: ni,
+                    expected: 0,
+                },
+            ));
+        }
+
+        self.blocks.pop();
+        Ok(())
+    }
+
+    fn enter(&mut self, name: &'static str) {
+        self.scopes.push(Scope { name, index: None });
+    }
+
+    fn enter_index(&mut self, name: &'static str, i: usize) {
+        self.scopes.push(Scope {
+            name,
+            index: Some(i),
+        });
+    }
+
+    fn leave(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn field(&mut self, name: &'static str) -> &mut Self {
+        self.field = Some(name);
+        self
+    }
+
+    fn error(&self, kind: ErrorKind) -> Error {
+        self.error_at(self.pos, kind)
+    }
+}
+
+/// Encodes a save file into any seekable sink. Block lengths are patched in
+/// place once a block is finished, which is why `Seek` is required.
+///
+/// Output is encrypted with the rolling XOR key set up by `write_key`, the
+/// same way the game does it. A zero seed yields an all-zero key, so every
+/// value ends up in the file as plain text.
+pub struct GDFile<S> {
+    f: S,
+    seed: u32,
+    key: u32,
+    table: [u32; 256],
+}
+
+impl<S> GDFile<S> {
+    pub fn new(f: S) -> Self {
+        Self {
+            f,
+            seed: 0,
+            key: 0,
+            table: [0; 256],
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.f
+    }
+
+    fn update_key(&mut self, val: &[u8]) {
+        for &i in val {
+            self.key ^= self.table[i as usize];
+        }
+    }
+}
+
+impl<S: Write + Seek> GDFile<S> {
+    fn error(&mut self, kind: ErrorKind) -> Error {
+        Error::new(
+            kind,
+            Location {
+                offset: self.f.stream_position().ok(),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn write_raw(&mut self, buf: &[u8]) -> Result<()> {
+        let ret = self.f.write(buf)?;
+        if ret != buf.len() {
+            return Err(self.error(ErrorKind::WriteAmount {
+                actual: ret,
+                expected: buf.len(),
+            }));
+        }
+        Ok(())
+    }
+
+    pub fn write_opaque_block(&mut self, b: &OpaqueBlock) -> Result<()> {
+        if self.seed != b.seed || self.key != b.key_in {
+            return Err(self.error(ErrorKind::OpaqueBlockKeyMismatch(b.seq)));
         }
 
+        self.write_raw(&b.raw)?;
+        self.key = b.key_out;
         Ok(())
     }
 }
 
-impl GDWriter for GDFile {
+impl<S: Write + Seek> GDWriter for GDFile<S> {
     fn write_vec<T: ReadWrite + Default>(&mut self, items: &[T]) -> Result<()> {
         self.write_int(items.len() as u32)?;
         self.write_arr(items)
@@ -246,29 +952,45 @@ impl GDWriter for GDFile {
     }
 
     fn write_string(&mut self, s: &str) -> Result<()> {
-        self.write_int(s.len() as u32)?;
-        for c in s.as_bytes() {
-            self.write_byte(*c)?;
+        // Latin-1, one byte per char, the way `read_string` decodes it
+        let mut bytes = Vec::with_capacity(s.len());
+        for c in s.chars() {
+            let Ok(b) = u8::try_from(c) else {
+                return Err(self.error(ErrorKind::NotLatin1(c)));
+            };
+            bytes.push(b);
+        }
+
+        self.write_int(bytes.len() as u32)?;
+        for b in bytes {
+            self.write_byte(b)?;
         }
         Ok(())
     }
 
     fn write_wstring(&mut self, s: &str) -> Result<()> {
-        self.write_int(s.chars().count() as u32)?;
-        let mut b = [0; 2];
-        for c in s.chars() {
-            c.encode_utf8(&mut b);
-            self.write_byte(b[0])?;
-            self.write_byte(b[1])?;
+        // the length prefix counts UTF-16 code units, not chars
+        self.write_int(s.encode_utf16().count() as u32)?;
+        for u in s.encode_utf16() {
+            let [lo, hi] = u.to_le_bytes();
+            self.write_byte(lo)?;
+            self.write_byte(hi)?;
         }
         Ok(())
     }
 
     fn write_byte(&mut self, v: u8) -> Result<()> {
-        let ret = self.f.write(&[v; 1])?;
-        if ret != 1 {
-            bail!(FileError::FailedToValidateWriteAmount(ret, 1))
-        }
+        let buf = [v ^ self.key as u8];
+        self.write_raw(&buf)?;
+        self.update_key(&buf);
+        Ok(())
+    }
+
+    fn write_key(&mut self, seed: u32) -> Result<()> {
+        self.write_raw(&u32::to_le_bytes(seed ^ 1431655765_u32))?;
+        self.seed = seed;
+        self.key = seed;
+        self.table = key_table(seed);
         Ok(())
     }
 
@@ -277,17 +999,20 @@ impl GDWriter for GDFile {
     }
 
     fn write_int(&mut self, v: u32) -> Result<()> {
-        let buf: [u8; 4] = u32::to_ne_bytes(v);
-        let ret = self.f.write(&buf)?;
-        if ret != 4 {
-            bail!(FileError::FailedToValidateWriteAmount(ret, 4))
-        }
+        let buf: [u8; 4] = u32::to_le_bytes(v ^ self.key);
+        self.write_raw(&buf)?;
+        self.update_key(&buf);
         Ok(())
     }
 
+    fn write_next_int(&mut self, v: u32) -> Result<()> {
+        self.write_raw(&u32::to_le_bytes(v ^ self.key))
+    }
+
     fn write_block_start(&mut self, b: &mut Block, n: u32) -> Result<()> {
         self.write_int(n)?;
-        self.write_int(0)?;
+        b.key = self.key;
+        self.write_next_int(0)?;
 
         b.end = self.f.stream_position()?;
 
@@ -296,10 +1021,13 @@ impl GDWriter for GDFile {
 
     fn write_block_end(&mut self, b: &mut Block) -> Result<()> {
         let pos = self.f.stream_position()?;
+        let Ok(len) = u32::try_from(pos - b.end) else {
+            return Err(self.error(ErrorKind::BlockTooLarge(pos - b.end)));
+        };
 
         self.f.seek(SeekFrom::Start(b.end - 4))?;
-        self.write_int((pos - b.end).try_into()?)?;
+        self.write_raw(&u32::to_le_bytes(len ^ b.key))?;
         self.f.seek(SeekFrom::Start(pos))?;
-        self.write_int(0)
+        self.write_next_int(0)
     }
 }
@@ -1,9 +1,10 @@
-use crate::gd::gd_file::{Block, GDReader, GDWriter};
-use anyhow::{Context, Result};
-use smart_default::SmartDefault;
+use crate::gd::diff::Scalar;
+
 use strum_macros::Display;
+use yagde_derive::GdBlock;
 
 #[derive(Default, Debug, Display, Clone, PartialEq, Eq, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Difficulty {
     #[default]
     Normal,
@@ -32,6 +33,7 @@ impl From<Difficulty> for u8 {
 }
 
 #[derive(Default, Debug, Display, Clone, PartialEq, Eq, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CrucibleDifficulty {
     #[default]
     Aspirant,
@@ -59,166 +61,89 @@ impl From<CrucibleDifficulty> for u8 {
     }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 1, versions = [3, 4, 5])]
 pub struct Info {
+    version: u32,
+    is_in_main_quest: u8,
+    has_been_in_game: u8,
+    // kept raw: the game stores flags next to the difficulty in this byte
+    difficulty: u8,
+    #[gd(as = u8)]
     pub greatest_difficulty: Difficulty,
-    pub greatest_crucible_difficulty: CrucibleDifficulty,
     pub money: u32,
-    difficulty: Difficulty,
-    alternate_config: u8,
-    alternate_config_enabled: u8,
-    compass_state: u8,
+    #[gd(since = 4, as = u8)]
+    pub greatest_crucible_difficulty: CrucibleDifficulty,
+    #[gd(since = 4)]
     current_tribute: u32,
-    has_been_in_game: u8,
-    is_in_main_quest: u8,
-    loot_filters: Vec<u8>,
+    compass_state: u8,
+    #[gd(since = 2, until = 4)]
     loot_mode: u32,
     skill_window_show_help: u8,
+    alternate_config: u8,
+    alternate_config_enabled: u8,
     texture: String,
-    version: u32,
-    #[default = 1]
-    block_seq: u32,
-    #[default(_code = "vec![3, 4, 5]")]
-    supported_versions: Vec<u32>,
-}
-
-impl Info {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-
-        f.write_int(self.version)?;
-
-        f.write_byte(self.is_in_main_quest)?;
-        f.write_byte(self.has_been_in_game)?;
-        f.write_byte(self.difficulty.into())?;
-        f.write_byte(self.greatest_difficulty.into())?;
-        f.write_int(self.money)?;
-
-        if self.version >= 4 {
-            f.write_byte(self.greatest_crucible_difficulty.into())?;
-            f.write_int(self.current_tribute)?;
-        }
-
-        f.write_byte(self.compass_state)?;
-
-        if self.version >= 2 && self.version <= 4 {
-            f.write_int(self.loot_mode)?;
-        }
-
-        f.write_byte(self.skill_window_show_help)?;
-        f.write_byte(self.alternate_config)?;
-        f.write_byte(self.alternate_config_enabled)?;
-        f.write_string(&self.texture)?;
-
-        if self.version >= 5 {
-            f.write_int(self.loot_filters.len() as u32)?;
-
-            for i in self.loot_filters.iter() {
-                f.write_byte(*i)?;
-            }
-        }
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in info")?;
-        self.is_in_main_quest = f.read_byte()?;
-        self.has_been_in_game = f.read_byte()?;
-        self.difficulty = f.read_byte()?.into();
-        self.greatest_difficulty = f.read_byte()?.into();
-        self.money = f.read_int()?;
-        if self.version >= 4 {
-            self.greatest_crucible_difficulty = f.read_byte()?.into();
-            self.current_tribute = f.read_int()?;
-        }
-        self.compass_state = f.read_byte()?;
-        if self.version >= 2 && self.version <= 4 {
-            self.loot_mode = f.read_int()?;
-        }
-        self.skill_window_show_help = f.read_byte()?;
-        self.alternate_config = f.read_byte()?;
-        self.alternate_config_enabled = f.read_byte()?;
-        self.texture = f.read_string()?;
-
-        if self.version >= 5 {
-            let size = f.read_int()?;
-            self.loot_filters = vec![0; size as usize];
-
-            for i in 0..self.loot_filters.len() {
-                self.loot_filters[i] = f.read_byte()?;
-            }
-        }
-
-        f.read_block_end(&mut b)
-    }
+    #[gd(since = 5)]
+    loot_filters: Vec<u8>,
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 2, versions = [8])]
 pub struct Bio {
+    version: u32,
+    level: u32,
     pub experience: u32,
+    pub attribute_points: u32,
     pub skill_points: u32,
+    pub devotion_points: u32,
+    pub total_devotion: u32,
     pub physique: f32,
     pub cunning: f32,
     pub spirit: f32,
-    pub attribute_points: u32,
-    pub devotion_points: u32,
     pub health: f32,
     pub energy: f32,
-    pub total_devotion: u32,
-    level: u32,
-    version: u32,
-    #[default = 2]
-    block_seq: u32,
-    #[default(_code = "vec![8]")]
-    supported_versions: Vec<u32>,
 }
 
-impl Bio {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-
-        f.write_int(8)?;
-        f.write_int(self.level)?;
-        f.write_int(self.experience)?;
-        f.write_int(self.attribute_points)?;
-        f.write_int(self.skill_points)?;
-        f.write_int(self.devotion_points)?;
-        f.write_int(self.total_devotion)?;
-        f.write_float(self.physique)?;
-        f.write_float(self.cunning)?;
-        f.write_float(self.spirit)?;
-        f.write_float(self.health)?;
-        f.write_float(self.energy)?;
-
-        f.write_block_end(&mut b)
+impl Info {
+    /// Fields not already shown as values of their own by `Char::diff`.
+    pub(crate) fn scalars(&self) -> Vec<(&'static str, Scalar)> {
+        vec![
+            ("Info.is_in_main_quest", self.is_in_main_quest.into()),
+            ("Info.has_been_in_game", self.has_been_in_game.into()),
+            ("Info.difficulty", self.difficulty.into()),
+            ("Info.current_tribute", self.current_tribute.into()),
+            ("Info.compass_state", self.compass_state.into()),
+            ("Info.loot_mode", self.loot_mode.into()),
+            (
+                "Info.skill_window_show_help",
+                self.skill_window_show_help.into(),
+            ),
+            ("Info.alternate_config", self.alternate_config.into()),
+            (
+                "Info.alternate_config_enabled",
+                self.alternate_config_enabled.into(),
+            ),
+            ("Info.texture", self.texture.clone().into()),
+        ]
     }
+}
 
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f.read_version(&self.supported_versions).context("in bio")?;
-        self.level = f.read_int()?;
-        self.experience = f.read_int()?;
-        self.attribute_points = f.read_int()?;
-        self.skill_points = f.read_int()?;
-        self.devotion_points = f.read_int()?;
-        self.total_devotion = f.read_int()?;
-        self.physique = f.read_float()?;
-        self.cunning = f.read_float()?;
-        self.spirit = f.read_float()?;
-        self.health = f.read_float()?;
-        self.energy = f.read_float()?;
-
-        f.read_block_end(&mut b)
+impl Bio {
+    pub(crate) fn scalars(&self) -> Vec<(&'static str, Scalar)> {
+        vec![
+            ("Bio.level", self.level.into()),
+            ("Bio.experience", self.experience.into()),
+            ("Bio.attribute_points", self.attribute_points.into()),
+            ("Bio.skill_points", self.skill_points.into()),
+            ("Bio.devotion_points", self.devotion_points.into()),
+            ("Bio.total_devotion", self.total_devotion.into()),
+            ("Bio.physique", self.physique.into()),
+            ("Bio.cunning", self.cunning.into()),
+            ("Bio.spirit", self.spirit.into()),
+            ("Bio.health", self.health.into()),
+            ("Bio.energy", self.energy.into()),
+        ]
     }
 }
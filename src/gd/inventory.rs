@@ -1,46 +1,38 @@
-use crate::gd::gd_file::{Block, GDReader, GDWriter, ReadWrite};
+use crate::gd::diff::Place;
+use crate::gd::gd_file::{Block, Count, GDReader, GDWriter, GdBlock, ReadWrite};
 use crate::gd::item::Item;
 
-use anyhow::{Context, Ok, Result};
-use smart_default::SmartDefault;
+use crate::gd::error::{ErrorKind, Result};
+use yagde_derive::GdBlock;
 
-#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "item")]
 struct StashItem {
+    #[gd(flatten)]
+    item: Item,
     x: f32,
     y: f32,
-    item: Item,
-}
-
-impl ReadWrite for StashItem {
-    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        self.item.write(f)?;
-        f.write_float(self.x)?;
-        f.write_float(self.y)
-    }
-
-    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.item.read(f)?;
-        self.x = f.read_float()?;
-        self.y = f.read_float()?;
-        Ok(())
-    }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct StashPage {
     width: u32,
     height: u32,
     version: u32,
     items: Vec<StashItem>,
-    #[default = 0]
-    block_seq: u32,
 }
 
 impl StashPage {
+    /// Width, height and the item count. Pages of version 6 also have a
+    /// block around them.
+    const MIN_SIZE: usize = 12;
+
     fn write(&self, f: &mut impl GDWriter) -> Result<()> {
         let mut b = Block::default();
         if self.version >= 6 {
-            f.write_block_start(&mut b, self.block_seq)?;
+            f.write_block_start(&mut b, 0)?;
         }
 
         f.write_int(self.width)?;
@@ -59,12 +51,12 @@ impl StashPage {
 
         let mut b = Block::default();
         if self.version >= 6 {
-            f.read_block_start(&mut b, self.block_seq)?;
+            f.read_block_start(&mut b, 0)?;
         }
 
-        self.width = f.read_int()?;
-        self.height = f.read_int()?;
-        self.items = f.read_vec()?;
+        self.width = f.field("width").read_int()?;
+        self.height = f.field("height").read_int()?;
+        self.items = f.field("items").read_vec()?;
 
         if self.version >= 6 {
             f.read_block_end(&mut b)?;
@@ -74,26 +66,84 @@ impl StashPage {
     }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stash {
     pages: Vec<StashPage>,
     version: u32,
-    num_pages: usize,
-    #[default = 4]
-    block_seq: u32,
-    #[default(_code = "vec![5, 6]")]
-    supported_versions: Vec<u32>,
 }
 
+// pages only have a block of their own since version 6, so this one can't be
+// derived
 impl Stash {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.pages.iter().map(|p| p.items.len()).sum()
+    }
+
+    pub(crate) fn items(&self) -> Vec<(&Item, Place)> {
+        let mut items = Vec::new();
+        for (page, p) in self.pages.iter().enumerate() {
+            for i in p.items.iter() {
+                items.push((
+                    &i.item,
+                    Place::Stash {
+                        page,
+                        x: i.x,
+                        y: i.y,
+                    },
+                ));
+            }
+        }
+        items
+    }
+}
+
+impl GdBlock for Stash {
+    const SEQ: u32 = 4;
+    const VERSIONS: &'static [u32] = &[5, 6];
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn migrate(&mut self, to: u32) -> Result<Vec<&'static str>, ErrorKind> {
+        Self::check_version(to)?;
+        let mut lost = Vec::new();
+
+        // before version 6 there is exactly one page
+        if to < 6 {
+            if self.pages.iter().skip(1).any(|p| !p.items.is_empty()) {
+                lost.push("pages");
+            }
+            self.pages.truncate(1);
+            if self.pages.is_empty() {
+                self.pages.push(StashPage::default());
+            }
+        }
+
+        for page in self.pages.iter_mut() {
+            page.version = to;
+        }
+        self.version = to;
+        Ok(lost)
+    }
+}
+
+impl ReadWrite for Stash {
+    const NAME: &'static str = "Stash";
+
+    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
         let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
+        f.write_block_start(&mut b, Self::SEQ)?;
 
         f.write_int(self.version)?;
 
         if self.version >= 6 {
-            f.write_int(self.num_pages as u32)?;
+            f.write_int(self.pages.len() as u32)?;
         }
 
         for page in self.pages.iter() {
@@ -103,22 +153,26 @@ impl Stash {
         f.write_block_end(&mut b)
     }
 
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+        // pages are pushed one by one, so start from an empty stash
+        *self = Self::default();
         let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
+        f.read_block_start(&mut b, Self::SEQ)?;
 
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in stash")?;
-        self.num_pages = 1;
+        self.version = f.read_version(Self::VERSIONS)?;
+        let mut n = 1;
         if self.version >= 6 {
-            self.num_pages = f.read_int()? as usize;
+            n = f
+                .field("num_pages")
+                .read_count(Count::StashPages, StashPage::MIN_SIZE)?;
         }
-        self.pages = Vec::with_capacity(self.num_pages);
+        self.pages = Vec::with_capacity(n);
 
-        for _ in 0..self.num_pages {
+        for i in 0..n {
             let mut page = StashPage::default();
+            f.enter_index("page", i);
             page.read(self.version, f)?;
+            f.leave();
             self.pages.push(page);
         }
 
@@ -126,77 +180,35 @@ impl Stash {
     }
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "item")]
 struct InventoryItem {
+    #[gd(flatten)]
+    item: Item,
     x: u32,
     y: u32,
-    item: Item,
-}
-
-impl ReadWrite for InventoryItem {
-    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.item.read(f)?;
-        self.x = f.read_int()?;
-        self.y = f.read_int()?;
-        Ok(())
-    }
-
-    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        self.item.write(f)?;
-        f.write_int(self.x)?;
-        f.write_int(self.y)
-    }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 0, name = "sack")]
 struct InventorySack {
-    items: Vec<InventoryItem>,
     temp_bool: u8,
-    #[default = 0]
-    block_seq: u32,
-}
-
-impl ReadWrite for InventorySack {
-    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.temp_bool = f.read_byte()?;
-        self.items = f.read_vec()?;
-
-        f.read_block_end(&mut b)
-    }
-
-    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-
-        f.write_byte(self.temp_bool)?;
-        f.write_vec(&self.items)?;
-
-        f.write_block_end(&mut b)
-    }
+    items: Vec<InventoryItem>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "equipment")]
 struct InventoryEquipment {
-    attached: u8,
+    #[gd(flatten)]
     item: Item,
+    attached: u8,
 }
 
-impl InventoryEquipment {
-    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.item.read(f)?;
-        self.attached = f.read_byte()?;
-        Ok(())
-    }
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        self.item.write(f)?;
-        f.write_byte(self.attached)
-    }
-}
-
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Inventory {
     version: u32,
     flag: u8,
@@ -209,16 +221,80 @@ pub struct Inventory {
     equipment: [InventoryEquipment; 12],
     weapon1: [InventoryEquipment; 2],
     weapon2: [InventoryEquipment; 2],
-    #[default = 3]
-    block_seq: u32,
-    #[default(_code = "vec![4, 5]")]
-    supported_versions: Vec<u32>,
 }
 
+// everything past the flag is only there when it's set, and the sack count
+// comes before two unrelated fields
 impl Inventory {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
+    pub fn sack_count(&self) -> usize {
+        self.sacks.len()
+    }
+
+    /// Items in all bags, equipment not included.
+    pub fn item_count(&self) -> usize {
+        self.sacks.iter().map(|s| s.items.len()).sum()
+    }
+
+    /// Filled equipment slots, both weapon sets included.
+    pub fn equipped_count(&self) -> usize {
+        self.equipment
+            .iter()
+            .chain(self.weapon1.iter())
+            .chain(self.weapon2.iter())
+            .filter(|e| !e.item.is_empty())
+            .count()
+    }
+
+    /// Items in the bags, then the filled equipment slots.
+    pub(crate) fn items(&self) -> Vec<(&Item, Place)> {
+        let mut items = Vec::new();
+        for (sack, s) in self.sacks.iter().enumerate() {
+            for i in s.items.iter() {
+                items.push((
+                    &i.item,
+                    Place::Sack {
+                        sack,
+                        x: i.x,
+                        y: i.y,
+                    },
+                ));
+            }
+        }
+        for (slot, e) in self.equipment.iter().enumerate() {
+            items.push((&e.item, Place::Equipment { slot }));
+        }
+        for (set, weapons) in [&self.weapon1, &self.weapon2].into_iter().enumerate() {
+            for (slot, e) in weapons.iter().enumerate() {
+                items.push((&e.item, Place::Weapon { set, slot }));
+            }
+        }
+        items.retain(|(item, _)| !item.is_empty());
+        items
+    }
+}
+
+impl GdBlock for Inventory {
+    const SEQ: u32 = 3;
+    const VERSIONS: &'static [u32] = &[4, 5];
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    // both versions share the same layout
+    fn migrate(&mut self, to: u32) -> Result<Vec<&'static str>, ErrorKind> {
+        Self::check_version(to)?;
+        self.version = to;
+        Ok(Vec::new())
+    }
+}
+
+impl ReadWrite for Inventory {
+    const NAME: &'static str = "Inventory";
+
+    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
         let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
+        f.write_block_start(&mut b, Self::SEQ)?;
         f.write_int(self.version)?;
         f.write_byte(self.flag)?;
 
@@ -228,53 +304,38 @@ impl Inventory {
             f.write_int(self.selected)?;
             f.write_arr(&self.sacks)?;
             f.write_byte(self.use_alternate)?;
-
-            for e in self.equipment.iter() {
-                e.write(f)?;
-            }
-
+            self.equipment.write(f)?;
             f.write_byte(self.alternate1)?;
-            for w in self.weapon1.iter() {
-                w.write(f)?;
-            }
+            self.weapon1.write(f)?;
             f.write_byte(self.alternate2)?;
-            for w in self.weapon2.iter() {
-                w.write(f)?;
-            }
+            self.weapon2.write(f)?;
         }
 
         f.write_block_end(&mut b)
     }
 
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+        // the sacks and equipment are only there when the flag is set
+        *self = Self::default();
         let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
+        f.read_block_start(&mut b, Self::SEQ)?;
 
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in inventory")?;
-        self.flag = f.read_byte()?;
+        self.version = f.read_version(Self::VERSIONS)?;
+        self.flag = f.field("flag").read_byte()?;
 
         if self.flag != 0 {
-            let n = f.read_int()? as usize;
-            self.focused = f.read_int()?;
-            self.selected = f.read_int()?;
+            let n = f
+                .field("sacks")
+                .read_count(Count::Sacks, InventorySack::MIN_SIZE)?;
+            self.focused = f.field("focused").read_int()?;
+            self.selected = f.field("selected").read_int()?;
             self.sacks = f.read_arr(n)?;
-
-            self.use_alternate = f.read_byte()?;
-            for i in 0..12 {
-                self.equipment[i].read(f)?;
-            }
-
-            self.alternate1 = f.read_byte()?;
-            for i in 0..2 {
-                self.weapon1[i].read(f)?;
-            }
-
-            self.alternate2 = f.read_byte()?;
-            for i in 0..2 {
-                self.weapon2[i].read(f)?;
-            }
+            self.use_alternate = f.field("use_alternate").read_byte()?;
+            self.equipment.read_named("equipment", f)?;
+            self.alternate1 = f.field("alternate1").read_byte()?;
+            self.weapon1.read_named("weapon set 1", f)?;
+            self.alternate2 = f.field("alternate2").read_byte()?;
+            self.weapon2.read_named("weapon set 2", f)?;
         }
         f.read_block_end(&mut b)
     }
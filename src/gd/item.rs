@@ -1,8 +1,10 @@
-use crate::gd::gd_file::{GDReader, GDWriter};
+use crate::gd::diff::Scalar;
 
-use anyhow::{Ok, Result};
+use yagde_derive::GdBlock;
 
-#[derive(Default, Debug, PartialEq, Eq, Clone)]
+#[derive(GdBlock, Default, Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "item")]
 pub struct Item {
     id: String,
     prefix_id: String,
@@ -14,46 +16,41 @@ pub struct Item {
     component_bonus: String,
     component_seed: u32,
     augment_id: String,
-    augment_seed: u32,
     unknown: u32,
+    augment_seed: u32,
     var1: u32,
     stack_count: u32,
+    #[gd(skip)]
     container_type: u32,
 }
 
 impl Item {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        f.write_string(&self.id)?;
-        f.write_string(&self.prefix_id)?;
-        f.write_string(&self.suffix_id)?;
-        f.write_string(&self.modifier_id)?;
-        f.write_string(&self.transmute_id)?;
-        f.write_int(self.seed)?;
-        f.write_string(&self.component_id)?;
-        f.write_string(&self.component_bonus)?;
-        f.write_int(self.component_seed)?;
-        f.write_string(&self.augment_id)?;
-        f.write_int(self.unknown)?;
-        f.write_int(self.augment_seed)?;
-        f.write_int(self.var1)?;
-        f.write_int(self.stack_count)
+    /// Empty equipment slots hold an item without a record.
+    pub fn is_empty(&self) -> bool {
+        self.id.is_empty()
+    }
+
+    /// The record the item was made from, e.g. `records/items/...dbr`.
+    pub fn id(&self) -> &str {
+        &self.id
     }
 
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.id = f.read_string()?;
-        self.prefix_id = f.read_string()?;
-        self.suffix_id = f.read_string()?;
-        self.modifier_id = f.read_string()?;
-        self.transmute_id = f.read_string()?;
-        self.seed = f.read_int()?;
-        self.component_id = f.read_string()?;
-        self.component_bonus = f.read_string()?;
-        self.component_seed = f.read_int()?;
-        self.augment_id = f.read_string()?;
-        self.unknown = f.read_int()?;
-        self.augment_seed = f.read_int()?;
-        self.var1 = f.read_int()?;
-        self.stack_count = f.read_int()?;
-        Ok(())
+    /// Everything but the record, for `Char::diff`.
+    pub(crate) fn scalars(&self) -> Vec<(&'static str, Scalar)> {
+        vec![
+            ("prefix_id", self.prefix_id.clone().into()),
+            ("suffix_id", self.suffix_id.clone().into()),
+            ("modifier_id", self.modifier_id.clone().into()),
+            ("transmute_id", self.transmute_id.clone().into()),
+            ("seed", self.seed.into()),
+            ("component_id", self.component_id.clone().into()),
+            ("component_bonus", self.component_bonus.clone().into()),
+            ("component_seed", self.component_seed.into()),
+            ("augment_id", self.augment_id.clone().into()),
+            ("unknown", self.unknown.into()),
+            ("augment_seed", self.augment_seed.into()),
+            ("var1", self.var1.into()),
+            ("stack_count", self.stack_count.into()),
+        ]
     }
 }
@@ -1,174 +1,44 @@
-use crate::gd::gd_file::{Block, GDReader, GDWriter, ReadWrite};
+use crate::gd::gd_file::{GDReader, GDWriter, ReadWrite};
 
-use anyhow::{Context, Ok, Result};
-use smart_default::SmartDefault;
+use crate::gd::error::Result;
 use std::ops::{Deref, DerefMut};
+use yagde_derive::GdBlock;
 
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 6, versions = [1])]
 pub struct TeleportList {
     version: u32,
     uids: [Vec<CharUID>; 3],
-    #[default = 6]
-    block_seq: u32,
-    #[default(_code = "vec![1]")]
-    supported_versions: Vec<u32>,
 }
 
-impl TeleportList {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-        f.write_int(self.version)?;
-
-        for i in self.uids.iter() {
-            f.write_vec(i)?;
-        }
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in teleport list")?;
-
-        for i in self.uids.iter_mut() {
-            i.extend(f.read_vec()?);
-        }
-
-        f.read_block_end(&mut b)
-    }
-}
-
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 5, versions = [1])]
 pub struct RespawnList {
     version: u32,
     uids: [Vec<CharUID>; 3],
     spawns: [CharUID; 3],
-    #[default = 5]
-    block_seq: u32,
-    #[default(_code = "vec![1]")]
-    supported_versions: Vec<u32>,
-}
-
-impl RespawnList {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-        f.write_int(self.version)?;
-
-        for i in self.uids.iter() {
-            f.write_vec(i)?;
-        }
-
-        for i in self.spawns.iter() {
-            i.write(f)?;
-        }
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in respawn list")?;
-
-        for i in self.uids.iter_mut() {
-            i.extend(f.read_vec()?);
-        }
-
-        for i in self.spawns.iter_mut() {
-            i.read(f)?;
-        }
-
-        f.read_block_end(&mut b)
-    }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 7, versions = [1])]
 pub struct MarkerList {
     version: u32,
     uids: [Vec<CharUID>; 3],
-    #[default = 7]
-    block_seq: u32,
-    #[default(_code = "vec![1]")]
-    supported_versions: Vec<u32>,
-}
-
-impl MarkerList {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-        f.write_int(self.version)?;
-
-        for i in self.uids.iter() {
-            f.write_vec(i)?;
-        }
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in marker list")?;
-
-        for i in self.uids.iter_mut() {
-            i.extend(f.read_vec()?);
-        }
-        f.read_block_end(&mut b)
-    }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 17, versions = [2])]
 pub struct ShrineList {
     version: u32,
     uids: [Vec<CharUID>; 6],
-    #[default = 17]
-    block_seq: u32,
-    #[default(_code = "vec![2]")]
-    supported_versions: Vec<u32>,
-}
-
-impl ShrineList {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-        f.write_int(self.version)?;
-
-        for i in self.uids.iter() {
-            f.write_vec(i)?;
-        }
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in shrine list")?;
-
-        for i in self.uids.iter_mut() {
-            i.extend(f.read_vec()?);
-        }
-
-        f.read_block_end(&mut b)
-    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CharUID([u8; 16]);
 
 impl std::fmt::Display for CharUID {
@@ -192,6 +62,9 @@ impl DerefMut for CharUID {
 }
 
 impl ReadWrite for CharUID {
+    const NAME: &'static str = "uid";
+    const MIN_SIZE: usize = 16;
+
     fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
         for i in 0..16 {
             self[i] = f.read_byte()?;
@@ -1,104 +1,26 @@
-use std::vec;
+use crate::gd::gd_file::{Block, Count, GDReader, GDWriter, GdBlock, ReadWrite};
 
-use crate::gd::gd_file::{Block, GDReader, GDWriter, ReadWrite};
+use crate::gd::error::{ErrorKind, Result};
+use yagde_derive::GdBlock;
 
-use anyhow::{Context, Ok, Result};
-use smart_default::SmartDefault;
-
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 10, versions = [2])]
 pub struct Crucible {
     version: u32,
     pub tokens_per_difficulty: [Vec<String>; 3],
-    #[default = 10]
-    block_seq: u32,
-    #[default(_code = "vec![2]")]
-    supported_versions: Vec<u32>,
-}
-
-impl Crucible {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-        f.write_int(self.version)?;
-
-        for t in self.tokens_per_difficulty.iter() {
-            f.write_int(t.len() as u32)?;
-
-            for s in t.iter() {
-                f.write_string(s)?;
-            }
-        }
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in crucible")?;
-
-        for i in 0..self.tokens_per_difficulty.len() {
-            for _ in 0..f.read_int()? {
-                let s = f.read_string()?;
-
-                if let Some(data) = self.tokens_per_difficulty.get_mut(i) {
-                    if data.contains(&s) {
-                        data.push(s);
-                    }
-                }
-            }
-        }
-
-        f.read_block_end(&mut b)
-    }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 15, versions = [1])]
 pub struct TutorialPages {
     version: u32,
     pages: Vec<u32>,
-    #[default = 15]
-    block_seq: u32,
-    #[default(_code = "vec![1]")]
-    supported_versions: Vec<u32>,
-}
-
-impl TutorialPages {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-        f.write_int(self.version)?;
-
-        f.write_int(self.pages.len() as u32)?;
-        for p in self.pages.iter() {
-            f.write_int(*p)?;
-        }
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in tutorial pages")?;
-        let n = f.read_int()? as usize;
-        self.pages = Vec::with_capacity(n);
-        for _ in 0..n {
-            let page = f.read_int()?;
-            self.pages.push(page);
-        }
-
-        f.read_block_end(&mut b)
-    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UISkillSet {
     id: u32,
     slots: Vec<UISlot>,
@@ -124,12 +46,13 @@ impl UISkillSet {
         Ok(())
     }
 
-    pub fn read(&mut self, version: u32, f: &mut impl GDReader) -> Result<()> {
+    /// Reads the id, if `version` has one, and `n` slots.
+    pub fn read(&mut self, version: u32, n: usize, f: &mut impl GDReader) -> Result<()> {
         if version >= 7 {
-            self.id = f.read_int()?;
+            self.id = f.field("id").read_int()?;
         }
 
-        for _ in 0..self.slots.capacity() {
+        for _ in 0..n {
             let mut slot = UISlot::default();
             slot.read(f)?;
             self.slots.push(slot);
@@ -140,6 +63,7 @@ impl UISkillSet {
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UISlot {
     bitmap_down: String,
     bitmap_up: String,
@@ -152,6 +76,9 @@ pub struct UISlot {
 }
 
 impl UISlot {
+    /// Just the slot type, empty slots have nothing else.
+    const MIN_SIZE: usize = 4;
+
     pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
         f.write_int(self.slot_type)?;
 
@@ -171,25 +98,26 @@ impl UISlot {
     }
 
     pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.slot_type = f.read_int()?;
+        self.slot_type = f.field("slot_type").read_int()?;
 
         if self.slot_type == 0 {
-            self.skill = f.read_string()?;
-            self.is_item_skill = f.read_byte()?;
-            self.item = f.read_string()?;
-            self.equip_location = f.read_int()?;
+            self.skill = f.field("skill").read_string()?;
+            self.is_item_skill = f.field("is_item_skill").read_byte()?;
+            self.item = f.field("item").read_string()?;
+            self.equip_location = f.field("equip_location").read_int()?;
         } else if self.slot_type == 4 {
-            self.item = f.read_string()?;
-            self.bitmap_up = f.read_string()?;
-            self.bitmap_down = f.read_string()?;
-            self.label = f.read_wstring()?;
+            self.item = f.field("item").read_string()?;
+            self.bitmap_up = f.field("bitmap_up").read_string()?;
+            self.bitmap_down = f.field("bitmap_down").read_string()?;
+            self.label = f.field("label").read_wstring()?;
         }
 
         Ok(())
     }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UI {
     version: u32,
     unknown1: u8,
@@ -202,16 +130,76 @@ pub struct UI {
     num_skill_set: u32,
     slots_per_set: u32,
     skill_sets: Vec<UISkillSet>,
-    #[default = 14]
-    block_seq: u32,
-    #[default(_code = "vec![4, 5, 6, 7]")]
-    supported_versions: Vec<u32>,
 }
 
+// the number and size of skill sets depend on the version in ways the derive
+// can't express
 impl UI {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
+    /// Number of slots in the single skill set of versions before 7, which
+    /// don't store it.
+    fn fixed_slots(version: u32) -> u32 {
+        match version {
+            ..=4 => 36,
+            5 => 46,
+            _ => 47,
+        }
+    }
+}
+
+impl GdBlock for UI {
+    const SEQ: u32 = 14;
+    const VERSIONS: &'static [u32] = &[4, 5, 6, 7];
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn migrate(&mut self, to: u32) -> Result<Vec<&'static str>, ErrorKind> {
+        Self::check_version(to)?;
+        let mut lost = Vec::new();
+
+        // before version 7 there is one skill set of a fixed size and no ids
+        if to < 7 {
+            let n = Self::fixed_slots(to) as usize;
+            let dropped = self.skill_sets.iter().skip(1).flat_map(|s| s.slots.iter());
+            let cut = self
+                .skill_sets
+                .iter()
+                .take(1)
+                .flat_map(|s| s.slots.iter().skip(n));
+            if dropped.chain(cut).any(|s| *s != UISlot::default()) {
+                lost.push("skill_sets");
+            }
+
+            self.skill_sets.truncate(1);
+            if self.skill_sets.is_empty() {
+                self.skill_sets.push(UISkillSet::with_slots(n));
+            }
+            for set in self.skill_sets.iter_mut() {
+                set.id = 0;
+                set.slots.resize(n, UISlot::default());
+            }
+            self.slots_per_set = n as u32;
+        } else if self.version < 7 {
+            let n = Self::fixed_slots(to);
+            for set in self.skill_sets.iter_mut() {
+                set.slots.resize(n as usize, UISlot::default());
+            }
+            self.slots_per_set = n;
+        }
+
+        self.num_skill_set = self.skill_sets.len() as u32;
+        self.version = to;
+        Ok(lost)
+    }
+}
+
+impl ReadWrite for UI {
+    const NAME: &'static str = "UI";
+
+    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
         let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
+        f.write_block_start(&mut b, Self::SEQ)?;
         f.write_int(self.version)?;
 
         f.write_byte(self.unknown1)?;
@@ -238,150 +226,75 @@ impl UI {
         f.write_block_end(&mut b)
     }
 
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
+        // skill sets are pushed one by one
+        *self = Self::default();
         let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
+        f.read_block_start(&mut b, Self::SEQ)?;
 
-        self.version = f.read_version(&self.supported_versions).context("in UI")?;
-        self.unknown1 = f.read_byte()?;
-        self.unknown2 = f.read_int()?;
-        self.unknown3 = f.read_byte()?;
+        self.version = f.read_version(Self::VERSIONS)?;
+        self.unknown1 = f.field("unknown1").read_byte()?;
+        self.unknown2 = f.field("unknown2").read_int()?;
+        self.unknown3 = f.field("unknown3").read_byte()?;
 
         for i in 0..self.unknown4.len() {
-            self.unknown4[i] = f.read_string()?;
-            self.unknown5[i] = f.read_string()?;
-            self.unknown6[i] = f.read_byte()?;
+            f.enter_index("unknown", i);
+            self.unknown4[i] = f.field("unknown4").read_string()?;
+            self.unknown5[i] = f.field("unknown5").read_string()?;
+            self.unknown6[i] = f.field("unknown6").read_byte()?;
+            f.leave();
         }
 
         if self.version >= 7 {
-            self.num_skill_set = f.read_int()?;
-            self.slots_per_set = f.read_int()?;
+            // every set has at least its id
+            self.num_skill_set = f.field("num_skill_set").read_count(Count::Items, 4)? as u32;
+            self.slots_per_set =
+                f.field("slots_per_set")
+                    .read_count(Count::Items, UISlot::MIN_SIZE)? as u32;
         } else {
             self.num_skill_set = 1;
-            if self.version >= 6 {
-                self.slots_per_set = 47;
-            } else if self.version == 5 {
-                self.slots_per_set = 46;
-            } else {
-                self.slots_per_set = 36;
-            }
+            self.slots_per_set = Self::fixed_slots(self.version);
         }
 
-        for _ in 0..self.num_skill_set {
-            let mut skill_set = UISkillSet::with_slots(self.slots_per_set as usize);
-            skill_set.read(self.version, f)?;
+        for i in 0..self.num_skill_set {
+            let n = self.slots_per_set as usize;
+            let mut skill_set = UISkillSet::with_slots(n);
+            f.enter_index("skill set", i as usize);
+            skill_set.read(self.version, n, f)?;
+            f.leave();
             self.skill_sets.push(skill_set);
         }
 
-        self.camera_distance = f.read_float()?;
+        self.camera_distance = f.field("camera_distance").read_float()?;
 
         f.read_block_end(&mut b)
     }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 13, versions = [5])]
 pub struct FactionList {
-    pub factions: Vec<Faction>,
     version: u32,
     faction: u32,
-    #[default = 13]
-    block_seq: u32,
-    #[default(_code = "vec![5]")]
-    supported_versions: Vec<u32>,
-}
-
-impl FactionList {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-
-        f.write_int(self.version)?;
-        f.write_int(self.faction)?;
-        f.write_vec(&self.factions)?;
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in faction list")?;
-        self.faction = f.read_int()?;
-        self.factions = f.read_vec()?;
-
-        f.read_block_end(&mut b)
-    }
+    pub factions: Vec<Faction>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "faction")]
 pub struct Faction {
-    pub value: f32,
     modified: u8,
     unlocked: u8,
+    pub value: f32,
     positive_boost: f32,
     negative_boost: f32,
 }
 
-impl ReadWrite for Faction {
-    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        f.write_byte(self.modified)?;
-        f.write_byte(self.unlocked)?;
-        f.write_float(self.value)?;
-        f.write_float(self.positive_boost)?;
-        f.write_float(self.negative_boost)
-    }
-
-    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.modified = f.read_byte()?;
-        self.unlocked = f.read_byte()?;
-        self.value = f.read_float()?;
-        self.positive_boost = f.read_float()?;
-        self.negative_boost = f.read_float()?;
-
-        Ok(())
-    }
-}
-
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 12, versions = [1])]
 pub struct NoteList {
     version: u32,
     notes: Vec<String>,
-    #[default = 12]
-    block_seq: u32,
-    #[default(_code = "vec![1]")]
-    supported_versions: Vec<u32>,
-}
-
-impl NoteList {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-        f.write_int(self.version)?;
-
-        f.write_int(self.notes.len() as u32)?;
-        for s in self.notes.iter() {
-            f.write_string(s)?;
-        }
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in note list")?;
-        let n = f.read_int()?;
-        for _ in 0..n {
-            let note = f.read_string()?;
-            self.notes.push(note);
-        }
-
-        f.read_block_end(&mut b)
-    }
 }
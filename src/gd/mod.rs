@@ -1,4 +1,7 @@
 pub mod char;
+pub mod diff;
+pub mod error;
+pub mod explain;
 mod gd_file;
 mod info;
 mod inventory;
@@ -7,3 +10,6 @@ mod lists;
 mod misc;
 mod skills;
 mod stats;
+pub mod summary;
+
+pub use gd_file::{Field, Limits, OpaqueBlock, Value};
@@ -1,9 +1,10 @@
-use crate::gd::gd_file::{Block, GDReader, GDWriter, ReadWrite};
+use crate::gd::diff::Scalar;
 
-use anyhow::{Context, Ok, Result};
-use smart_default::SmartDefault;
+use yagde_derive::GdBlock;
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "sub skill")]
 struct SubSkill {
     name: String,
     auto_cast_skill: String,
@@ -11,25 +12,9 @@ struct SubSkill {
     parent_skill: String,
 }
 
-impl ReadWrite for SubSkill {
-    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        f.write_string(&self.name)?;
-        f.write_string(&self.auto_cast_skill)?;
-        f.write_string(&self.auto_cast_controller)?;
-        f.write_string(&self.parent_skill)
-    }
-
-    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.name = f.read_string()?;
-        self.auto_cast_skill = f.read_string()?;
-        self.auto_cast_controller = f.read_string()?;
-        self.parent_skill = f.read_string()?;
-
-        Ok(())
-    }
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "item skill")]
 struct ItemSkill {
     name: String,
     auto_cast_skill: String,
@@ -38,31 +23,13 @@ struct ItemSkill {
     item_id: String,
 }
 
-impl ReadWrite for ItemSkill {
-    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        f.write_string(&self.name)?;
-        f.write_string(&self.auto_cast_skill)?;
-        f.write_string(&self.auto_cast_controller)?;
-        f.write_int(self.item_slot)?;
-        f.write_string(&self.item_id)
-    }
-
-    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.name = f.read_string()?;
-        self.auto_cast_skill = f.read_string()?;
-        self.auto_cast_controller = f.read_string()?;
-        self.item_slot = f.read_int()?;
-        self.item_id = f.read_string()?;
-
-        Ok(())
-    }
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "skill")]
 pub struct Skill {
     pub name: String,
-    pub enabled: u8,
     pub level: u32,
+    pub enabled: u8,
     pub devotion_level: u32,
     experience: u32,
     active: u32,
@@ -72,85 +39,35 @@ pub struct Skill {
     pub auto_cast_controller: String,
 }
 
-impl ReadWrite for Skill {
-    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        f.write_string(&self.name)?;
-        f.write_int(self.level)?;
-        f.write_byte(self.enabled)?;
-        f.write_int(self.devotion_level)?;
-        f.write_int(self.experience)?;
-        f.write_int(self.active)?;
-        f.write_byte(self.unknown1)?;
-        f.write_byte(self.unknown2)?;
-        f.write_string(&self.auto_cast_skill)?;
-        f.write_string(&self.auto_cast_controller)
-    }
-
-    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.name = f.read_string()?;
-        self.level = f.read_int()?;
-        self.enabled = f.read_byte()?;
-        self.devotion_level = f.read_int()?;
-        self.experience = f.read_int()?;
-        self.active = f.read_int()?;
-        self.unknown1 = f.read_byte()?;
-        self.unknown2 = f.read_byte()?;
-        self.auto_cast_skill = f.read_string()?;
-        self.auto_cast_controller = f.read_string()?;
-
-        Ok(())
+impl Skill {
+    /// Everything but the name and level, for `Char::diff`.
+    pub(crate) fn scalars(&self) -> Vec<(&'static str, Scalar)> {
+        vec![
+            ("enabled", self.enabled.into()),
+            ("devotion_level", self.devotion_level.into()),
+            ("experience", self.experience.into()),
+            ("active", self.active.into()),
+            ("unknown1", self.unknown1.into()),
+            ("unknown2", self.unknown2.into()),
+            ("auto_cast_skill", self.auto_cast_skill.clone().into()),
+            (
+                "auto_cast_controller",
+                self.auto_cast_controller.clone().into(),
+            ),
+        ]
     }
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 8, versions = [5, 6])]
 pub struct SkillList {
+    version: u32,
+    pub skills: Vec<Skill>,
+    masteries_allowed: u32,
     pub skill_reclamation_points_used: u32,
     pub devotion_reclamation_points_used: u32,
-    pub skills: Vec<Skill>,
-    version: u32,
     item_skills: Vec<ItemSkill>,
+    #[gd(since = 6)]
     sub_skills: Vec<SubSkill>,
-    masteries_allowed: u32,
-    #[default = 8]
-    block_seq: u32,
-    #[default(_code = "vec![5, 6]")]
-    supported_versions: Vec<u32>,
-}
-
-impl SkillList {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-
-        f.write_int(self.version)?;
-        f.write_vec(&self.skills)?;
-        f.write_int(self.masteries_allowed)?;
-        f.write_int(self.skill_reclamation_points_used)?;
-        f.write_int(self.devotion_reclamation_points_used)?;
-        f.write_vec(&self.item_skills)?;
-        if self.version >= 6 {
-            f.write_vec(&self.sub_skills)?;
-        }
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in skill list")?;
-        self.skills = f.read_vec()?;
-        self.masteries_allowed = f.read_int()?;
-        self.skill_reclamation_points_used = f.read_int()?;
-        self.devotion_reclamation_points_used = f.read_int()?;
-        self.item_skills = f.read_vec()?;
-        if self.version >= 6 {
-            self.sub_skills = f.read_vec()?;
-        }
-
-        f.read_block_end(&mut b)
-    }
 }
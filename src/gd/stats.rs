@@ -1,209 +1,156 @@
-use crate::gd::gd_file::{Block, GDReader, GDWriter, ReadWrite};
+use crate::gd::diff::Scalar;
 
-use anyhow::{Context, Ok, Result};
-use smart_default::SmartDefault;
+use yagde_derive::GdBlock;
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "skill map")]
 struct SkillMap {
     skill: String,
     active: u32,
 }
 
-impl ReadWrite for SkillMap {
-    fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        f.write_string(&self.skill)?;
-        f.write_int(self.active)
-    }
-
-    fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        self.skill = f.read_string()?;
-        self.active = f.read_int()?;
-
-        Ok(())
-    }
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(name = "difficulty")]
 struct StatsPerDifficulty {
     greatest_monster_killed_name: String,
     greatest_monster_killed_level: u32,
     greatest_monster_killed_life_and_mana: u32,
     last_monster_hit: String,
     last_monster_hit_by: String,
-    nemesis_kills: u32,
 }
 
-#[derive(SmartDefault, Debug, Clone, PartialEq)]
+#[derive(GdBlock, Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[gd(block = 16, versions = [7, 9, 11])]
 pub struct Stats {
-    pub champion_kills: u32,
+    version: u32,
+    pub playtime: u32,
     pub deaths: u32,
-    pub hero_kills: u32,
     pub kills: u32,
-    pub playtime: u32,
-
-    version: u32,
-    critical_hits_inflicted: u32,
-    critical_hits_received: u32,
-    difficulty_skip: u8,
-    endless_essence: u32,
-    endless_souls: u32,
     experience_from_kills: u32,
-    greatest_damage_inflicted: f32,
-    greatest_damage_received: f32,
     health_potions_used: u32,
-    hits_inflicted: u32,
+    mana_potions_used: u32,
+    max_level: u32,
     hits_received: u32,
-    items_crafted: u32,
+    hits_inflicted: u32,
+    critical_hits_inflicted: u32,
+    critical_hits_received: u32,
+    greatest_damage_inflicted: f32,
+    stats_difficulty: [StatsPerDifficulty; 3],
+    pub champion_kills: u32,
     last_hit: f32,
     last_hit_by: f32,
-    lore_notes_collected: u32,
-    mana_potions_used: u32,
-    max_level: u32,
-    mythical_relics_crafted: u32,
-    one_shot_chests_opened: u32,
+    greatest_damage_received: f32,
+    pub hero_kills: u32,
+    items_crafted: u32,
     relics_crafted: u32,
+    transcendent_relics_crafted: u32,
+    mythical_relics_crafted: u32,
     shrines_restored: u32,
-    skill_map: Vec<SkillMap>,
-    stats_difficulty: [StatsPerDifficulty; 3],
-    survival_defenses_built: u32,
-    survival_greatest_score: u32,
+    one_shot_chests_opened: u32,
+    lore_notes_collected: u32,
+    // per difficulty as well, but stored apart from the other counters
+    nemesis_kills: [u32; 3],
+    #[gd(since = 9)]
     survival_greatest_wave: u32,
+    #[gd(since = 9)]
+    survival_greatest_score: u32,
+    #[gd(since = 9)]
+    survival_defenses_built: u32,
+    #[gd(since = 9)]
     survival_powerups_activated: u32,
-    transcendent_relics_crafted: u32,
+    #[gd(since = 11)]
+    skill_map: Vec<SkillMap>,
+    #[gd(since = 11)]
+    endless_souls: u32,
+    #[gd(since = 11)]
+    endless_essence: u32,
+    #[gd(since = 11)]
+    difficulty_skip: u8,
     unknown1: u32,
     unknown2: u32,
-    #[default = 16]
-    block_seq: u32,
-    #[default(_code = "vec![7, 9, 11]")]
-    supported_versions: Vec<u32>,
 }
 
 impl Stats {
-    pub fn write(&self, f: &mut impl GDWriter) -> Result<()> {
-        let mut b = Block::default();
-        f.write_block_start(&mut b, self.block_seq)?;
-        f.write_int(self.version)?;
-
-        f.write_int(self.playtime)?;
-        f.write_int(self.deaths)?;
-        f.write_int(self.kills)?;
-        f.write_int(self.experience_from_kills)?;
-        f.write_int(self.health_potions_used)?;
-        f.write_int(self.mana_potions_used)?;
-        f.write_int(self.max_level)?;
-        f.write_int(self.hits_received)?;
-        f.write_int(self.hits_inflicted)?;
-        f.write_int(self.critical_hits_inflicted)?;
-        f.write_int(self.critical_hits_received)?;
-        f.write_float(self.greatest_damage_inflicted)?;
-
-        for d in self.stats_difficulty.iter() {
-            f.write_string(&d.greatest_monster_killed_name)?;
-            f.write_int(d.greatest_monster_killed_level)?;
-            f.write_int(d.greatest_monster_killed_life_and_mana)?;
-            f.write_string(&d.last_monster_hit)?;
-            f.write_string(&d.last_monster_hit_by)?;
-        }
-
-        f.write_int(self.champion_kills)?;
-        f.write_float(self.last_hit)?;
-        f.write_float(self.last_hit_by)?;
-        f.write_float(self.greatest_damage_received)?;
-        f.write_int(self.hero_kills)?;
-        f.write_int(self.items_crafted)?;
-        f.write_int(self.relics_crafted)?;
-        f.write_int(self.transcendent_relics_crafted)?;
-        f.write_int(self.mythical_relics_crafted)?;
-        f.write_int(self.shrines_restored)?;
-        f.write_int(self.one_shot_chests_opened)?;
-        f.write_int(self.lore_notes_collected)?;
-
-        for d in self.stats_difficulty.iter() {
-            f.write_int(d.nemesis_kills)?;
-        }
-
-        if self.version >= 9 {
-            f.write_int(self.survival_greatest_wave)?;
-            f.write_int(self.survival_greatest_score)?;
-            f.write_int(self.survival_defenses_built)?;
-            f.write_int(self.survival_powerups_activated)?;
-        }
-
-        if self.version >= 11 {
-            f.write_vec(&self.skill_map)?;
-            f.write_int(self.endless_souls)?;
-            f.write_int(self.endless_essence)?;
-            f.write_byte(self.difficulty_skip)?;
-        }
-
-        f.write_int(self.unknown1)?;
-        f.write_int(self.unknown2)?;
-
-        f.write_block_end(&mut b)
-    }
-
-    pub fn read(&mut self, f: &mut impl GDReader) -> Result<()> {
-        let mut b = Block::default();
-        f.read_block_start(&mut b, self.block_seq)?;
-
-        self.version = f
-            .read_version(&self.supported_versions)
-            .context("in stats")?;
-        self.playtime = f.read_int()?;
-        self.deaths = f.read_int()?;
-        self.kills = f.read_int()?;
-        self.experience_from_kills = f.read_int()?;
-        self.health_potions_used = f.read_int()?;
-        self.mana_potions_used = f.read_int()?;
-        self.max_level = f.read_int()?;
-        self.hits_received = f.read_int()?;
-        self.hits_inflicted = f.read_int()?;
-        self.critical_hits_inflicted = f.read_int()?;
-        self.critical_hits_received = f.read_int()?;
-        self.greatest_damage_inflicted = f.read_float()?;
-
-        for i in 0..self.stats_difficulty.len() {
-            self.stats_difficulty[i].greatest_monster_killed_name = f.read_string()?;
-            self.stats_difficulty[i].greatest_monster_killed_level = f.read_int()?;
-            self.stats_difficulty[i].greatest_monster_killed_life_and_mana = f.read_int()?;
-            self.stats_difficulty[i].last_monster_hit = f.read_string()?;
-            self.stats_difficulty[i].last_monster_hit_by = f.read_string()?;
-        }
-
-        self.champion_kills = f.read_int()?;
-        self.last_hit = f.read_float()?;
-        self.last_hit_by = f.read_float()?;
-        self.greatest_damage_received = f.read_float()?;
-        self.hero_kills = f.read_int()?;
-        self.items_crafted = f.read_int()?;
-        self.relics_crafted = f.read_int()?;
-        self.transcendent_relics_crafted = f.read_int()?;
-        self.mythical_relics_crafted = f.read_int()?;
-        self.shrines_restored = f.read_int()?;
-        self.one_shot_chests_opened = f.read_int()?;
-        self.lore_notes_collected = f.read_int()?;
-
-        for i in 0..self.stats_difficulty.len() {
-            self.stats_difficulty[i].nemesis_kills = f.read_int()?;
-        }
-
-        if self.version >= 9 {
-            self.survival_greatest_wave = f.read_int()?;
-            self.survival_greatest_score = f.read_int()?;
-            self.survival_defenses_built = f.read_int()?;
-            self.survival_powerups_activated = f.read_int()?;
-        }
-
-        if self.version >= 11 {
-            self.skill_map = f.read_vec()?;
-            self.endless_souls = f.read_int()?;
-            self.endless_essence = f.read_int()?;
-            self.difficulty_skip = f.read_byte()?;
-        }
-
-        self.unknown1 = f.read_int()?;
-        self.unknown2 = f.read_int()?;
-
-        f.read_block_end(&mut b)
+    /// The counters, in save order. Names and per difficulty records are
+    /// left out.
+    pub(crate) fn scalars(&self) -> Vec<(&'static str, Scalar)> {
+        vec![
+            ("Stats.playtime", self.playtime.into()),
+            ("Stats.deaths", self.deaths.into()),
+            ("Stats.kills", self.kills.into()),
+            (
+                "Stats.experience_from_kills",
+                self.experience_from_kills.into(),
+            ),
+            ("Stats.health_potions_used", self.health_potions_used.into()),
+            ("Stats.mana_potions_used", self.mana_potions_used.into()),
+            ("Stats.max_level", self.max_level.into()),
+            ("Stats.hits_received", self.hits_received.into()),
+            ("Stats.hits_inflicted", self.hits_inflicted.into()),
+            (
+                "Stats.critical_hits_inflicted",
+                self.critical_hits_inflicted.into(),
+            ),
+            (
+                "Stats.critical_hits_received",
+                self.critical_hits_received.into(),
+            ),
+            (
+                "Stats.greatest_damage_inflicted",
+                self.greatest_damage_inflicted.into(),
+            ),
+            ("Stats.champion_kills", self.champion_kills.into()),
+            ("Stats.last_hit", self.last_hit.into()),
+            ("Stats.last_hit_by", self.last_hit_by.into()),
+            (
+                "Stats.greatest_damage_received",
+                self.greatest_damage_received.into(),
+            ),
+            ("Stats.hero_kills", self.hero_kills.into()),
+            ("Stats.items_crafted", self.items_crafted.into()),
+            ("Stats.relics_crafted", self.relics_crafted.into()),
+            (
+                "Stats.transcendent_relics_crafted",
+                self.transcendent_relics_crafted.into(),
+            ),
+            (
+                "Stats.mythical_relics_crafted",
+                self.mythical_relics_crafted.into(),
+            ),
+            ("Stats.shrines_restored", self.shrines_restored.into()),
+            (
+                "Stats.one_shot_chests_opened",
+                self.one_shot_chests_opened.into(),
+            ),
+            (
+                "Stats.lore_notes_collected",
+                self.lore_notes_collected.into(),
+            ),
+            ("Stats.nemesis_kills[0]", self.nemesis_kills[0].into()),
+            ("Stats.nemesis_kills[1]", self.nemesis_kills[1].into()),
+            ("Stats.nemesis_kills[2]", self.nemesis_kills[2].into()),
+            (
+                "Stats.survival_greatest_wave",
+                self.survival_greatest_wave.into(),
+            ),
+            (
+                "Stats.survival_greatest_score",
+                self.survival_greatest_score.into(),
+            ),
+            (
+                "Stats.survival_defenses_built",
+                self.survival_defenses_built.into(),
+            ),
+            (
+                "Stats.survival_powerups_activated",
+                self.survival_powerups_activated.into(),
+            ),
+            ("Stats.endless_souls", self.endless_souls.into()),
+            ("Stats.endless_essence", self.endless_essence.into()),
+            ("Stats.difficulty_skip", self.difficulty_skip.into()),
+        ]
     }
 }
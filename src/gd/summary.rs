@@ -0,0 +1,183 @@
+use crate::gd::char::{block_name, Char, ExpansionStatus, Header, Sex};
+use crate::gd::info::{CrucibleDifficulty, Difficulty};
+
+/// The public parts of a character, as shown by `yagde view`, in a shape
+/// meant for other programs rather than for reading.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Summary {
+    pub uid: String,
+    pub header: HeaderSummary,
+    pub money: u32,
+    pub greatest_difficulty: Difficulty,
+    pub greatest_crucible_difficulty: CrucibleDifficulty,
+    pub bio: BioSummary,
+    pub stats: StatsSummary,
+    pub skills: SkillsSummary,
+    /// Reputation with every faction, in save order.
+    pub factions: Vec<f32>,
+    pub inventory: InventorySummary,
+    /// Blocks a tolerant read kept as raw bytes.
+    pub skipped_blocks: Vec<SkippedBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HeaderSummary {
+    pub name: String,
+    pub sex: Sex,
+    pub level: u32,
+    pub hardcore: bool,
+    pub expansion_status: ExpansionStatus,
+    pub class_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BioSummary {
+    pub experience: u32,
+    pub attribute_points: u32,
+    pub skill_points: u32,
+    pub devotion_points: u32,
+    pub total_devotion: u32,
+    pub physique: f32,
+    pub cunning: f32,
+    pub spirit: f32,
+    pub health: f32,
+    pub energy: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StatsSummary {
+    /// In seconds.
+    pub playtime: u32,
+    pub deaths: u32,
+    pub kills: u32,
+    pub champion_kills: u32,
+    pub hero_kills: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SkillsSummary {
+    pub skill_reclamation_points_used: u32,
+    pub devotion_reclamation_points_used: u32,
+    pub skills: Vec<SkillSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SkillSummary {
+    pub name: String,
+    pub level: u32,
+    pub enabled: bool,
+    pub devotion_level: u32,
+    pub auto_cast_skill: String,
+    pub auto_cast_controller: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InventorySummary {
+    pub sacks: usize,
+    /// Items in the bags, equipment not included.
+    pub items: usize,
+    pub equipped: usize,
+    pub stash_pages: usize,
+    pub stash_items: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SkippedBlock {
+    pub seq: u32,
+    pub name: String,
+    pub version: u32,
+    pub len: usize,
+}
+
+impl From<&Header> for HeaderSummary {
+    fn from(h: &Header) -> Self {
+        Self {
+            name: h.name.clone(),
+            sex: h.sex,
+            level: h.level,
+            hardcore: h.hardcore != 0,
+            expansion_status: h.expansion_status,
+            class_id: h.class_id.clone(),
+        }
+    }
+}
+
+impl From<&Char> for Summary {
+    fn from(c: &Char) -> Self {
+        Self {
+            uid: c.uid.to_string(),
+            header: (&c.header).into(),
+            money: c.info.money,
+            greatest_difficulty: c.info.greatest_difficulty,
+            greatest_crucible_difficulty: c.info.greatest_crucible_difficulty,
+            bio: BioSummary {
+                experience: c.bio.experience,
+                attribute_points: c.bio.attribute_points,
+                skill_points: c.bio.skill_points,
+                devotion_points: c.bio.devotion_points,
+                total_devotion: c.bio.total_devotion,
+                physique: c.bio.physique,
+                cunning: c.bio.cunning,
+                spirit: c.bio.spirit,
+                health: c.bio.health,
+                energy: c.bio.energy,
+            },
+            stats: StatsSummary {
+                playtime: c.stats.playtime,
+                deaths: c.stats.deaths,
+                kills: c.stats.kills,
+                champion_kills: c.stats.champion_kills,
+                hero_kills: c.stats.hero_kills,
+            },
+            skills: SkillsSummary {
+                skill_reclamation_points_used: c.skills.skill_reclamation_points_used,
+                devotion_reclamation_points_used: c.skills.devotion_reclamation_points_used,
+                skills: c
+                    .skills
+                    .skills
+                    .iter()
+                    .map(|s| SkillSummary {
+                        name: s.name.clone(),
+                        level: s.level,
+                        enabled: s.enabled != 0,
+                        devotion_level: s.devotion_level,
+                        auto_cast_skill: s.auto_cast_skill.clone(),
+                        auto_cast_controller: s.auto_cast_controller.clone(),
+                    })
+                    .collect(),
+            },
+            factions: c.factions.factions.iter().map(|f| f.value).collect(),
+            inventory: InventorySummary {
+                sacks: c.inventory.sack_count(),
+                items: c.inventory.item_count(),
+                equipped: c.inventory.equipped_count(),
+                stash_pages: c.stash.page_count(),
+                stash_items: c.stash.item_count(),
+            },
+            skipped_blocks: c
+                .opaque_blocks()
+                .iter()
+                .map(|b| SkippedBlock {
+                    seq: b.seq,
+                    name: block_name(b.seq).to_owned(),
+                    version: b.version,
+                    len: b.len(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Char {
+    pub fn summary(&self) -> Summary {
+        self.into()
+    }
+}
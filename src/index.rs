@@ -0,0 +1,281 @@
+use crate::discover::{mod_name, mod_roots};
+use crate::gd::char::{Char, Header};
+use crate::gd::error::{Error, ErrorKind, Result};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::SystemTime;
+
+use tracing::debug;
+
+/// Whether a save can be read: its header, or all of it when the index
+/// checks health.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    Ok,
+    /// Saved by a game version this build doesn't know.
+    Unsupported {
+        version: u32,
+    },
+    /// Not a save, damaged or unreadable, from `offset` on if the error
+    /// points at one.
+    Corrupt {
+        offset: Option<u64>,
+        reason: String,
+    },
+}
+
+impl From<&Error> for Health {
+    fn from(e: &Error) -> Self {
+        match &e.kind {
+            ErrorKind::UnsupportedVersion { actual, .. } => {
+                Health::Unsupported { version: *actual }
+            }
+            kind => Health::Corrupt {
+                offset: e.offset(),
+                reason: kind.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Health::Ok => write!(f, "ok"),
+            Health::Unsupported { version } => write!(f, "unsupported version {version}"),
+            Health::Corrupt {
+                offset: Some(offset),
+                reason,
+            } => write!(f, "corrupt at offset {offset:#06x}: {reason}"),
+            Health::Corrupt {
+                offset: None,
+                reason,
+            } => write!(f, "corrupt: {reason}"),
+        }
+    }
+}
+
+/// A character found in a save root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharEntry {
+    pub dir: PathBuf,
+    /// Empty unless `health` is ok.
+    pub header: Header,
+    /// Mod folder the character is saved in, `None` for the main game.
+    pub mod_name: Option<String>,
+    pub health: Health,
+}
+
+impl CharEntry {
+    pub fn file(&self) -> PathBuf {
+        self.dir.join("player.gdc")
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.health == Health::Ok
+    }
+
+    /// Name with level, hardcore flag, expansion and mod, as shown in menus.
+    /// Broken saves show their directory and what's wrong instead.
+    pub fn label(&self) -> String {
+        let mod_name = match &self.mod_name {
+            Some(m) => format!(", mod {m}"),
+            None => String::new(),
+        };
+        if !self.is_ok() {
+            let dir = self.dir.file_name().unwrap_or_default().to_string_lossy();
+            return format!("{dir} ({}{mod_name})", self.health);
+        }
+        format!(
+            "{} (lvl {}, {}, {}{mod_name})",
+            self.header.name,
+            self.header.level,
+            if self.header.hardcore != 0 {
+                "HC"
+            } else {
+                "SC"
+            },
+            self.header.expansion_status
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Cached {
+    modified: SystemTime,
+    size: u64,
+    header: Header,
+    health: Health,
+    /// Read in full rather than just the header.
+    checked: bool,
+}
+
+/// Index of all characters in a save root.
+///
+/// Only headers are read, in parallel, unless `with_health_check` asks for
+/// the whole saves. Results are cached by file mtime and size, so rescanning
+/// a root only touches saves that changed in between. Saves that can't be
+/// read are listed with their `Health` rather than failing the scan.
+#[derive(Debug, Default)]
+pub struct SaveIndex {
+    root: PathBuf,
+    mods: bool,
+    health_check: bool,
+    cache: HashMap<PathBuf, Cached>,
+}
+
+impl SaveIndex {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            mods: false,
+            health_check: false,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Also lists the characters of the mod folders next to a `save/main`
+    /// root.
+    pub fn with_mods(&mut self, mods: bool) -> &mut Self {
+        self.mods = mods;
+        self
+    }
+
+    /// Reads every save in full, the tolerant way they are opened for
+    /// editing, so an ok save is one that opens. Slower than the header
+    /// alone, which misses damage past it.
+    pub fn with_health_check(&mut self, health_check: bool) -> &mut Self {
+        self.health_check = health_check;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Lists all characters of the root, sorted by name, then those of the
+    /// mod folders by mod and name.
+    pub fn scan(&mut self) -> Result<Vec<CharEntry>> {
+        let mut roots = vec![self.root.clone()];
+        if self.mods {
+            roots.extend(mod_roots(&self.root));
+        }
+
+        let mut dirs = Vec::new();
+        for root in roots.iter() {
+            dirs.extend(fs::read_dir(root)?.filter_map(|e| e.ok()).map(|e| e.path()));
+        }
+
+        let mut files = Vec::new();
+        for dir in dirs {
+            // backups and other service folders
+            if dir
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().contains("__"))
+            {
+                continue;
+            }
+            let file = dir.join("player.gdc");
+            let Ok(meta) = fs::metadata(&file) else {
+                continue;
+            };
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((dir, file, modified, meta.len()));
+        }
+
+        let stale: Vec<&(PathBuf, PathBuf, SystemTime, u64)> = files
+            .iter()
+            .filter(|(_, file, modified, size)| {
+                !matches!(self.cache.get(file), Some(c) if c.modified == *modified
+                    && c.size == *size
+                    && (c.checked || !self.health_check))
+            })
+            .collect();
+        debug!(total = files.len(), stale = stale.len(), "scanning saves");
+
+        let paths: Vec<&PathBuf> = stale.iter().map(|(_, file, _, _)| file).collect();
+        let headers = read_saves(&paths, self.health_check);
+        for ((_, file, modified, size), header) in stale.into_iter().zip(headers) {
+            let (header, health) = match header {
+                Ok(h) => (h, Health::Ok),
+                Err(e) => {
+                    debug!(?file, %e, "broken save");
+                    (Header::default(), Health::from(&e))
+                }
+            };
+            self.cache.insert(
+                file.clone(),
+                Cached {
+                    modified: *modified,
+                    size: *size,
+                    header,
+                    health,
+                    checked: self.health_check,
+                },
+            );
+        }
+
+        self.cache
+            .retain(|file, _| files.iter().any(|(_, f, _, _)| f == file));
+
+        let mut entries: Vec<CharEntry> = files
+            .into_iter()
+            .map(|(dir, file, _, _)| CharEntry {
+                mod_name: dir.parent().and_then(mod_name),
+                dir,
+                header: self.cache[&file].header.clone(),
+                health: self.cache[&file].health.clone(),
+            })
+            .collect();
+        // broken saves last, by directory
+        entries.sort_by(|a, b| {
+            let key = |c: &CharEntry| (c.mod_name.clone(), !c.is_ok(), c.header.name.clone());
+            key(a).cmp(&key(b)).then_with(|| a.dir.cmp(&b.dir))
+        });
+
+        Ok(entries)
+    }
+}
+
+/// Reads headers, or whole saves with `full`, on all available cores,
+/// keeping the order of `files`.
+fn read_saves(files: &[&PathBuf], full: bool) -> Vec<Result<Header>> {
+    if files.is_empty() {
+        return Vec::new();
+    }
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = files.len().div_ceil(threads);
+
+    thread::scope(|s| {
+        let handles: Vec<_> = files
+            .chunks(chunk)
+            .map(|c| {
+                s.spawn(move || {
+                    c.iter()
+                        .map(|f| match full {
+                            true => read_save(f),
+                            false => Char::read_header(f),
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut headers = Vec::with_capacity(files.len());
+        for h in handles {
+            headers.extend(h.join().expect("save reader panicked"));
+        }
+        headers
+    })
+}
+
+fn read_save(file: &Path) -> Result<Header> {
+    let mut c = Char::new();
+    c.with_tolerant(true).read(&file.to_path_buf())?;
+    Ok(c.header)
+}
@@ -1 +1,10 @@
+pub mod backup;
+pub mod config;
+pub mod discover;
 pub mod gd;
+pub mod index;
+pub mod session;
+pub mod sync;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod util;
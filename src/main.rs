@@ -1,8 +1,13 @@
 mod app;
-mod gd;
 
-use anyhow::Result;
+use std::process::ExitCode;
 
-fn main() -> Result<()> {
-    app::run()
+fn main() -> ExitCode {
+    match app::run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            app::exit_code(&e)
+        }
+    }
 }
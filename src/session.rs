@@ -0,0 +1,116 @@
+use crate::gd::char::Char;
+use crate::gd::diff::Change;
+use crate::gd::error::Result;
+
+use std::path::{Path, PathBuf};
+
+/// A character being edited, with its changes kept in memory until saved.
+///
+/// Every edit pushes the previous state on an undo stack, so a series of
+/// edits can be stepped back and forth before anything is written.
+#[derive(Debug, Clone)]
+pub struct Session {
+    file: PathBuf,
+    saved: Char,
+    current: Char,
+    undo: Vec<Char>,
+    redo: Vec<Char>,
+}
+
+impl Session {
+    pub fn new(file: &Path, c: Char) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            saved: c.clone(),
+            current: c,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Reads `file` tolerantly, like the menus always did.
+    pub fn open(file: &Path) -> Result<Self> {
+        let mut c = Char::new();
+        c.with_tolerant(true).read(&file.to_path_buf())?;
+        Ok(Self::new(file, c))
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// The character with all edits so far.
+    pub fn char(&self) -> &Char {
+        &self.current
+    }
+
+    /// Applies `edit` and returns what it changed, or `None` if it left the
+    /// character as it was, in which case there is nothing to undo either.
+    /// Edits a read-only character couldn't be saved with are refused, see
+    /// `Char::check_writable`.
+    pub fn edit(&mut self, edit: impl FnOnce(&mut Char)) -> Result<Option<Vec<Change>>> {
+        let mut edited = self.current.clone();
+        edit(&mut edited);
+        if edited == self.current {
+            return Ok(None);
+        }
+        edited.check_writable()?;
+
+        let changes = self.current.diff(&edited);
+        self.undo.push(std::mem::replace(&mut self.current, edited));
+        self.redo.clear();
+        Ok(Some(changes))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Steps back one edit, returns false when there is none.
+    pub fn undo(&mut self) -> bool {
+        let Some(previous) = self.undo.pop() else {
+            return false;
+        };
+        self.redo
+            .push(std::mem::replace(&mut self.current, previous));
+        true
+    }
+
+    /// Reapplies the last undone edit, returns false when there is none.
+    pub fn redo(&mut self) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(std::mem::replace(&mut self.current, next));
+        true
+    }
+
+    /// Whether there are edits that aren't written yet.
+    pub fn is_dirty(&self) -> bool {
+        self.current != self.saved
+    }
+
+    /// Everything that saving would change in the file.
+    pub fn pending(&self) -> Vec<Change> {
+        self.saved.diff(&self.current)
+    }
+
+    /// Goes back to the saved character. This counts as an edit, so it can
+    /// be undone as well.
+    pub fn discard(&mut self) -> bool {
+        let saved = self.saved.clone();
+        // the saved character always writes back
+        matches!(self.edit(|c| *c = saved), Ok(Some(_)))
+    }
+
+    /// Writes the edits to the file.
+    pub fn save(&mut self) -> Result<()> {
+        self.current.save_as(&self.file)?;
+        self.saved = self.current.clone();
+        Ok(())
+    }
+}
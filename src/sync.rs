@@ -0,0 +1,274 @@
+use crate::backup::timestamp;
+use crate::gd::char::{Char, Header};
+use crate::util::{recover_staging, replace_dir};
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use tracing::debug;
+
+/// One of the two save folders being synced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    Cloud,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Local => write!(f, "local"),
+            Side::Cloud => write!(f, "cloud"),
+        }
+    }
+}
+
+impl Side {
+    pub fn other(self) -> Side {
+        match self {
+            Side::Local => Side::Cloud,
+            Side::Cloud => Side::Local,
+        }
+    }
+}
+
+/// How the copies of a character compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Both copies are the same file.
+    InSync,
+    /// The copy to keep, or the only one.
+    Newer(Side),
+    /// The copies differ, but their age can't be told apart. Only the user
+    /// can pick the one to keep.
+    Conflict,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            State::InSync => write!(f, "in sync"),
+            State::Newer(side) => write!(f, "{side} is newer"),
+            State::Conflict => write!(f, "conflict, both differ and look equally new"),
+        }
+    }
+}
+
+/// A character as saved in one of the folders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Saved {
+    pub dir: PathBuf,
+    pub header: Header,
+    /// Seconds played.
+    pub playtime: u32,
+    /// Last write of its `player.gdc`.
+    pub modified: SystemTime,
+}
+
+impl fmt::Display for Saved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lvl {}, {}h{:02}m played, saved {}",
+            self.header.level,
+            self.playtime / 3600,
+            self.playtime % 3600 / 60,
+            timestamp(self.modified)
+        )
+    }
+}
+
+/// The copies of one character, by `CharUID`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pair {
+    pub uid: String,
+    pub local: Option<Saved>,
+    pub cloud: Option<Saved>,
+    pub state: State,
+}
+
+impl Pair {
+    pub fn name(&self) -> &str {
+        match (&self.local, &self.cloud) {
+            (Some(c), _) | (None, Some(c)) => &c.header.name,
+            (None, None) => "",
+        }
+    }
+
+    pub fn get(&self, side: Side) -> Option<&Saved> {
+        match side {
+            Side::Local => self.local.as_ref(),
+            Side::Cloud => self.cloud.as_ref(),
+        }
+    }
+}
+
+/// Pairs the characters of a local save folder with those of its Steam
+/// Cloud copy.
+///
+/// Copies are matched by `CharUID`, so renamed characters or different
+/// directory names still pair up, and by directory name among characters
+/// sharing one. The one played the longest is newer, then
+/// the one of higher level, then the one written last. Copies that differ
+/// but tie on all three are a conflict.
+#[derive(Debug, Clone)]
+pub struct CloudSync {
+    local: PathBuf,
+    cloud: PathBuf,
+}
+
+impl CloudSync {
+    pub fn new(local: &Path, cloud: &Path) -> Self {
+        Self {
+            local: local.to_path_buf(),
+            cloud: cloud.to_path_buf(),
+        }
+    }
+
+    pub fn root(&self, side: Side) -> &Path {
+        match side {
+            Side::Local => &self.local,
+            Side::Cloud => &self.cloud,
+        }
+    }
+
+    /// All characters of both folders, by name. Saves that can't be read are
+    /// left out. Characters an interrupted sync had moved aside are put back
+    /// first.
+    pub fn pairs(&self) -> io::Result<Vec<Pair>> {
+        recover_staging(&self.local)?;
+        recover_staging(&self.cloud)?;
+        let mut found: Vec<([u8; 16], Pair)> = Vec::new();
+        for side in [Side::Local, Side::Cloud] {
+            for (uid, display, copy) in read_copies(self.root(side))? {
+                // clones keep the uid of their original, so several
+                // characters of a folder can share one
+                let open: Vec<usize> = (0..found.len())
+                    .filter(|&i| found[i].0 == uid && found[i].1.get(side).is_none())
+                    .collect();
+                let same_dir = open.iter().copied().find(|&i| {
+                    let other = found[i].1.local.as_ref().or(found[i].1.cloud.as_ref());
+                    other.is_some_and(|o| o.dir.file_name() == copy.dir.file_name())
+                });
+                let i = match same_dir.or(open.first().copied()) {
+                    Some(i) => i,
+                    None => {
+                        found.push((
+                            uid,
+                            Pair {
+                                uid: display,
+                                local: None,
+                                cloud: None,
+                                state: State::InSync,
+                            },
+                        ));
+                        found.len() - 1
+                    }
+                };
+                match side {
+                    Side::Local => found[i].1.local = Some(copy),
+                    Side::Cloud => found[i].1.cloud = Some(copy),
+                }
+            }
+        }
+
+        let mut pairs: Vec<Pair> = Vec::with_capacity(found.len());
+        for (_, mut pair) in found {
+            pair.state = state(&pair)?;
+            pairs.push(pair);
+        }
+        pairs.sort_by(|a, b| a.name().cmp(b.name()));
+        Ok(pairs)
+    }
+
+    /// Copies the copy of `pair` on `side` over the other one, or next to
+    /// the other characters when that folder has none. Which side to keep is
+    /// up to the caller: the newer one, or the one picked in a conflict.
+    /// `before` gets the `player.gdc` about to be replaced first, e.g. to
+    /// back it up, and its error is returned as is.
+    pub fn apply<E: From<io::Error>>(
+        &self,
+        pair: &Pair,
+        side: Side,
+        before: impl FnOnce(&Path) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let other = side.other();
+        let Some(from) = pair.get(side).map(|c| c.dir.clone()) else {
+            return Ok(());
+        };
+        let to = match pair.get(other) {
+            Some(c) => c.dir.clone(),
+            None => {
+                let to = self.root(other).join(from.file_name().unwrap_or_default());
+                if to.exists() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} holds another character", to.display()),
+                    )
+                    .into());
+                }
+                to
+            }
+        };
+
+        before(&to.join("player.gdc"))?;
+        replace_dir(&from, &to)?;
+        debug!(?from, ?to, "synced");
+        Ok(())
+    }
+}
+
+fn read_copies(root: &Path) -> io::Result<Vec<([u8; 16], String, Saved)>> {
+    let mut copies = Vec::new();
+    for e in fs::read_dir(root)? {
+        let dir = e?.path();
+        if dir
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().contains("__"))
+        {
+            continue;
+        }
+        let file = dir.join("player.gdc");
+        let Ok(meta) = fs::metadata(&file) else {
+            continue;
+        };
+        let mut c = Char::new();
+        if let Err(e) = c.with_tolerant(true).read(&file) {
+            debug!(?file, %e, "skipped");
+            continue;
+        }
+        copies.push((
+            *c.uid,
+            c.uid.to_string(),
+            Saved {
+                dir,
+                header: c.header,
+                playtime: c.stats.playtime,
+                modified: meta.modified()?,
+            },
+        ));
+    }
+    copies.sort_by(|a, b| a.2.dir.cmp(&b.2.dir));
+    Ok(copies)
+}
+
+fn state(pair: &Pair) -> io::Result<State> {
+    let (local, cloud) = match (&pair.local, &pair.cloud) {
+        (Some(l), Some(c)) => (l, c),
+        (Some(_), None) => return Ok(State::Newer(Side::Local)),
+        (None, _) => return Ok(State::Newer(Side::Cloud)),
+    };
+    if fs::read(local.dir.join("player.gdc"))? == fs::read(cloud.dir.join("player.gdc"))? {
+        return Ok(State::InSync);
+    }
+
+    let age = |c: &Saved| (c.playtime, c.header.level, c.modified);
+    Ok(match age(local).cmp(&age(cloud)) {
+        std::cmp::Ordering::Greater => State::Newer(Side::Local),
+        std::cmp::Ordering::Less => State::Newer(Side::Cloud),
+        std::cmp::Ordering::Equal => State::Conflict,
+    })
+}
@@ -0,0 +1,602 @@
+use crate::gd::char::Char;
+use crate::gd::diff::Place;
+use crate::session::Session;
+
+use std::io;
+use std::path::Path;
+
+use ratatui::backend::Backend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState, Tabs};
+use ratatui::{Frame, Terminal};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+pub enum Pane {
+    Character,
+    Skills,
+    Devotions,
+    Factions,
+    Inventory,
+    Stats,
+}
+
+/// What the caller of `App::handle_key` has to do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    /// Write the session, e.g. through `App::save`.
+    Save,
+}
+
+/// A field that can be edited in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Money,
+    Experience,
+    AttributePoints,
+    SkillPoints,
+    DevotionPoints,
+    Physique,
+    Cunning,
+    Spirit,
+    Health,
+    Energy,
+    SkillLevel(usize),
+    Faction(usize),
+}
+
+impl Field {
+    /// Parses `input` before touching `c`, so a typo leaves it as it was.
+    fn apply(self, c: &mut Char, input: &str) -> Result<(), String> {
+        let input = input.trim();
+        let int = || {
+            input
+                .parse::<u32>()
+                .map_err(|_| format!("{input:?} is not a whole number"))
+        };
+        let float = || {
+            input
+                .parse::<f32>()
+                .map_err(|_| format!("{input:?} is not a number"))
+        };
+
+        match self {
+            Self::Name if input.is_empty() => return Err("the name can't be empty".to_owned()),
+            Self::Name => {
+                c.rename(input);
+            }
+            Self::Money => c.info.money = int()?,
+            Self::Experience => c.bio.experience = int()?,
+            Self::AttributePoints => c.bio.attribute_points = int()?,
+            Self::SkillPoints => c.bio.skill_points = int()?,
+            Self::DevotionPoints => c.bio.devotion_points = int()?,
+            Self::Physique => c.bio.physique = float()?,
+            Self::Cunning => c.bio.cunning = float()?,
+            Self::Spirit => c.bio.spirit = float()?,
+            Self::Health => c.bio.health = float()?,
+            Self::Energy => c.bio.energy = float()?,
+            Self::SkillLevel(i) => c.skills.skills[i].level = int()?,
+            Self::Faction(i) => c.factions.factions[i].value = float()?,
+        }
+        Ok(())
+    }
+}
+
+/// One row of a pane.
+struct Entry {
+    label: String,
+    value: String,
+    note: String,
+    field: Option<Field>,
+}
+
+impl Entry {
+    fn new(label: impl Into<String>, value: impl ToString) -> Self {
+        Self {
+            label: label.into(),
+            value: value.to_string(),
+            note: String::new(),
+            field: None,
+        }
+    }
+
+    fn edit(mut self, field: Field) -> Self {
+        self.field = Some(field);
+        self
+    }
+
+    fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = note.into();
+        self
+    }
+}
+
+/// Items of one sack, the equipment or one stash page.
+struct Container {
+    name: String,
+    grid: bool,
+    items: Vec<(String, Place)>,
+}
+
+/// Full-screen editor of one character.
+///
+/// Edits go through a `Session`, so they can be undone and are only written
+/// on save. The state is separate from the terminal: tests drive it with
+/// `handle_key` and draw it on a `TestBackend`.
+pub struct App {
+    session: Session,
+    pane: Pane,
+    selected: [usize; 6],
+    container: usize,
+    input: Option<(Field, String)>,
+    status: String,
+    quit: bool,
+    quit_asked: bool,
+}
+
+const DEVOTION: &str = "records/skills/devotion";
+
+impl App {
+    pub fn new(session: Session) -> Self {
+        let status = match session.char().is_read_only() {
+            true => "Read-only: the save is encrypted and has blocks kept as is".to_owned(),
+            false => String::new(),
+        };
+        Self {
+            session,
+            pane: Pane::Character,
+            selected: [0; 6],
+            container: 0,
+            input: None,
+            status,
+            quit: false,
+            quit_asked: false,
+        }
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn pane(&self) -> Pane {
+        self.pane
+    }
+
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Writes the session, after `before` had a look at the file, e.g. to
+    /// back it up.
+    pub fn save(&mut self, before: impl FnOnce(&Path) -> io::Result<()>) {
+        let saved = before(self.session.file())
+            .map_err(|e| e.to_string())
+            .and_then(|_| self.session.save().map_err(|e| e.to_string()));
+        self.status = match saved {
+            Ok(()) => "Saved".to_owned(),
+            Err(e) => format!("Save failed: {e}"),
+        };
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Request> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        if self.input.is_some() {
+            self.handle_input(key.code);
+            return None;
+        }
+
+        let quit_asked = std::mem::take(&mut self.quit_asked);
+        let last = self.entries().len().max(1) - 1;
+        let selected = self.selected[self.pane as usize];
+        match key.code {
+            KeyCode::Tab => self.pane = self.next_pane(1),
+            KeyCode::BackTab => self.pane = self.next_pane(Pane::iter().count() - 1),
+            KeyCode::Char(c @ '1'..='6') => {
+                self.pane = Pane::iter().nth(c as usize - '1' as usize).unwrap()
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.select((selected + 1).min(last)),
+            KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)),
+            KeyCode::PageDown => self.select((selected + 10).min(last)),
+            KeyCode::PageUp => self.select(selected.saturating_sub(10)),
+            KeyCode::Home => self.select(0),
+            KeyCode::End => self.select(last),
+            KeyCode::Left | KeyCode::Char('h') if self.pane == Pane::Inventory => {
+                self.container = self.container.saturating_sub(1);
+                self.selected[Pane::Inventory as usize] = 0;
+            }
+            KeyCode::Right | KeyCode::Char('l') if self.pane == Pane::Inventory => {
+                self.container = (self.container + 1).min(self.containers().len() - 1);
+                self.selected[Pane::Inventory as usize] = 0;
+            }
+            KeyCode::Enter => self.start_edit(),
+            KeyCode::Char('u') => {
+                self.status = match self.session.undo() {
+                    true => "Undone".to_owned(),
+                    false => "Nothing to undo".to_owned(),
+                }
+            }
+            KeyCode::Char('r') => {
+                self.status = match self.session.redo() {
+                    true => "Redone".to_owned(),
+                    false => "Nothing to redo".to_owned(),
+                }
+            }
+            KeyCode::Char('d') => {
+                self.status = match self.session.discard() {
+                    true => "Unsaved changes discarded, u brings them back".to_owned(),
+                    false => "Nothing to discard".to_owned(),
+                }
+            }
+            KeyCode::Char('s') if self.session.is_dirty() => return Some(Request::Save),
+            KeyCode::Char('s') => self.status = "Nothing to save".to_owned(),
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if !self.session.is_dirty() || quit_asked {
+                    self.quit = true;
+                } else {
+                    self.quit_asked = true;
+                    self.status =
+                        "Unsaved changes: press q again to quit without saving, s to save"
+                            .to_owned();
+                }
+            }
+            _ => {}
+        }
+        self.clamp();
+        None
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let [tabs, body, status, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let c = self.session.char();
+        let mut title = format!(" {} ", c.header.name);
+        if self.session.is_dirty() {
+            title.push_str("[modified] ");
+        }
+        let titles = Pane::iter()
+            .enumerate()
+            .map(|(i, p)| format!("{} {p}", i + 1));
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(self.pane as usize)
+                .highlight_style(Style::new().bold().reversed())
+                .block(Block::bordered().title(title)),
+            tabs,
+        );
+
+        if self.pane == Pane::Inventory {
+            self.draw_inventory(frame, body);
+        } else {
+            self.draw_entries(frame, body, &self.entries(), self.pane.to_string());
+        }
+
+        let line = match &self.input {
+            Some((_, text)) => Line::from(vec![Span::raw("New value: "), Span::raw(text).bold()]),
+            None => Line::from(self.status.as_str()),
+        };
+        frame.render_widget(Paragraph::new(line), status);
+        frame.render_widget(
+            Paragraph::new(
+                "Tab pane  ↑↓ select  ←→ bag  Enter edit  u undo  r redo  d discard  s save  q quit",
+            )
+            .dim(),
+            help,
+        );
+    }
+
+    fn draw_entries(&self, frame: &mut Frame, area: Rect, entries: &[Entry], title: String) {
+        let rows = entries.iter().map(|e| {
+            let value = match e.field {
+                Some(_) => Span::raw(e.value.as_str()).bold(),
+                None => Span::raw(e.value.as_str()),
+            };
+            Row::new(vec![
+                Line::from(e.label.as_str()),
+                Line::from(value),
+                Line::from(e.note.as_str()).dim(),
+            ])
+        });
+        let width = |f: fn(&Entry) -> &str| {
+            entries
+                .iter()
+                .map(|e| f(e).chars().count() as u16)
+                .max()
+                .unwrap_or(0)
+        };
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(width(|e| &e.value).max(10)),
+                Constraint::Length(width(|e| &e.note)),
+            ],
+        )
+        .block(Block::bordered().title(format!(" {title} ")))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        let mut state = TableState::default();
+        if !entries.is_empty() {
+            state.select(Some(self.selected[self.pane as usize]));
+        }
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn draw_inventory(&self, frame: &mut Frame, area: Rect) {
+        let containers = self.containers();
+        let container = &containers[self.container];
+        let title = format!(
+            "{} ◀ {}/{} ▶",
+            container.name,
+            self.container + 1,
+            containers.len()
+        );
+        if !container.grid {
+            self.draw_entries(frame, area, &self.entries(), title);
+            return;
+        }
+
+        let [grid, list] =
+            Layout::horizontal([Constraint::Length(30), Constraint::Min(0)]).areas(area);
+        let cells: Vec<(u32, u32)> = container
+            .items
+            .iter()
+            .map(|(_, p)| match *p {
+                Place::Sack { x, y, .. } => (x, y),
+                Place::Stash { x, y, .. } => (x as u32, y as u32),
+                _ => (0, 0),
+            })
+            .collect();
+        let width = cells.iter().map(|c| c.0 + 1).max().unwrap_or(0).max(8);
+        let height = cells.iter().map(|c| c.1 + 1).max().unwrap_or(0).max(8);
+        let selected = self.selected[Pane::Inventory as usize];
+
+        let lines: Vec<Line> = (0..height)
+            .map(|y| {
+                Line::from(
+                    (0..width)
+                        .map(|x| match cells.iter().position(|c| *c == (x, y)) {
+                            Some(i) if i == selected => Span::raw(marker(i)).reversed(),
+                            Some(i) => Span::raw(marker(i)).bold(),
+                            None => Span::raw("· ").dim(),
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(format!(" {title} "))),
+            grid,
+        );
+        self.draw_entries(frame, list, &self.entries(), "Items".to_owned());
+    }
+
+    fn select(&mut self, row: usize) {
+        self.selected[self.pane as usize] = row;
+    }
+
+    fn next_pane(&self, step: usize) -> Pane {
+        let n = Pane::iter().count();
+        Pane::iter().nth((self.pane as usize + step) % n).unwrap()
+    }
+
+    fn clamp(&mut self) {
+        self.container = self.container.min(self.containers().len() - 1);
+        let rows = self.entries().len();
+        let selected = &mut self.selected[self.pane as usize];
+        *selected = (*selected).min(rows.max(1) - 1);
+    }
+
+    fn start_edit(&mut self) {
+        let entries = self.entries();
+        match entries.get(self.selected[self.pane as usize]) {
+            Some(Entry {
+                field: Some(field),
+                value,
+                ..
+            }) => {
+                self.input = Some((*field, value.clone()));
+                self.status.clear();
+            }
+            Some(e) => self.status = format!("{} can't be edited here", e.label),
+            None => {}
+        }
+    }
+
+    fn handle_input(&mut self, key: KeyCode) {
+        let Some((field, text)) = &mut self.input else {
+            return;
+        };
+        match key {
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Enter => {
+                let (field, text) = (*field, std::mem::take(text));
+                self.input = None;
+                let mut error = None;
+                let changes = self.session.edit(|c| {
+                    if let Err(e) = field.apply(c, &text) {
+                        error = Some(e);
+                    }
+                });
+                self.status = match (error, changes) {
+                    (Some(e), _) => e,
+                    (None, Err(e)) => e.to_string(),
+                    (None, Ok(None)) => "Nothing changed".to_owned(),
+                    (None, Ok(Some(changes))) => changes
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                };
+                self.clamp();
+            }
+            _ => {}
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry> {
+        let c = self.session.char();
+        match self.pane {
+            Pane::Character => {
+                let (h, b) = (&c.header, &c.bio);
+                vec![
+                    Entry::new("Name", &h.name).edit(Field::Name),
+                    Entry::new("Class", short(&h.class_id)),
+                    Entry::new("Level", h.level),
+                    Entry::new("Hardcore", h.hardcore != 0),
+                    Entry::new("Expansions", h.expansion_status),
+                    Entry::new("UID", &c.uid),
+                    Entry::new("Iron", c.info.money).edit(Field::Money),
+                    Entry::new("Max difficulty", c.info.greatest_difficulty),
+                    Entry::new(
+                        "Max crucible difficulty",
+                        c.info.greatest_crucible_difficulty,
+                    ),
+                    Entry::new("Experience", b.experience).edit(Field::Experience),
+                    Entry::new("Attribute points", b.attribute_points).edit(Field::AttributePoints),
+                    Entry::new("Skill points", b.skill_points).edit(Field::SkillPoints),
+                    Entry::new("Devotion points", b.devotion_points).edit(Field::DevotionPoints),
+                    Entry::new("Total devotion points", b.total_devotion),
+                    Entry::new("Physique", b.physique).edit(Field::Physique),
+                    Entry::new("Cunning", b.cunning).edit(Field::Cunning),
+                    Entry::new("Spirit", b.spirit).edit(Field::Spirit),
+                    Entry::new("Health", b.health).edit(Field::Health),
+                    Entry::new("Energy", b.energy).edit(Field::Energy),
+                ]
+            }
+            Pane::Skills | Pane::Devotions => c
+                .skills
+                .skills
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.name.starts_with(DEVOTION) == (self.pane == Pane::Devotions))
+                .map(|(i, s)| {
+                    let note = match (s.enabled, s.devotion_level) {
+                        (0, _) => "disabled".to_owned(),
+                        (_, l) if l > 1 => format!("celestial power, level {l}"),
+                        _ => String::new(),
+                    };
+                    Entry::new(short(&s.name), s.level)
+                        .edit(Field::SkillLevel(i))
+                        .note(note)
+                })
+                .collect(),
+            Pane::Factions => c
+                .factions
+                .factions
+                .iter()
+                .enumerate()
+                .map(|(i, f)| Entry::new(format!("Faction {i}"), f.value).edit(Field::Faction(i)))
+                .collect(),
+            Pane::Inventory => self.containers()[self.container]
+                .items
+                .iter()
+                .enumerate()
+                .map(|(i, (item, place))| {
+                    let label = match container_grid(place) {
+                        true => format!("{} {}", marker(i).trim(), place),
+                        false => place.to_string(),
+                    };
+                    Entry::new(label, short(item))
+                })
+                .collect(),
+            Pane::Stats => c
+                .stats
+                .scalars()
+                .into_iter()
+                .map(|(name, v)| Entry::new(name.trim_start_matches("Stats."), v))
+                .collect(),
+        }
+    }
+
+    fn containers(&self) -> Vec<Container> {
+        let c = self.session.char();
+        let mut containers: Vec<Container> = (0..c.inventory.sack_count())
+            .map(|i| Container {
+                name: format!("Bag {i}"),
+                grid: true,
+                items: Vec::new(),
+            })
+            .collect();
+        containers.push(Container {
+            name: "Equipped".to_owned(),
+            grid: false,
+            items: Vec::new(),
+        });
+        let equipped = containers.len() - 1;
+        containers.extend((0..c.stash.page_count()).map(|i| Container {
+            name: format!("Stash page {i}"),
+            grid: true,
+            items: Vec::new(),
+        }));
+
+        for (item, place) in c.items() {
+            let i = match place {
+                Place::Sack { sack, .. } => sack,
+                Place::Equipment { .. } | Place::Weapon { .. } => equipped,
+                Place::Stash { page, .. } => equipped + 1 + page,
+            };
+            containers[i].items.push((item.id().to_owned(), place));
+        }
+        containers
+    }
+}
+
+fn container_grid(place: &Place) -> bool {
+    matches!(place, Place::Sack { .. } | Place::Stash { .. })
+}
+
+/// Letter of the `i`th item of a grid, two cells wide.
+fn marker(i: usize) -> String {
+    const MARKERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    format!("{} ", MARKERS[i % MARKERS.len()] as char)
+}
+
+/// `records/skills/playerclass06/natureblessing1.dbr` as
+/// `playerclass06/natureblessing1`.
+fn short(record: &str) -> String {
+    let record = record.trim_end_matches(".dbr");
+    let mut parts = record.rsplitn(3, '/');
+    match (parts.next(), parts.next()) {
+        (Some(name), Some(dir)) => format!("{dir}/{name}"),
+        _ => record.to_owned(),
+    }
+}
+
+/// Runs `app` on `terminal` until it quits. `before_save` runs before every
+/// write, e.g. to back up the file.
+pub fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mut before_save: impl FnMut(&Path) -> io::Result<()>,
+) -> io::Result<()> {
+    while !app.should_quit() {
+        terminal.draw(|f| app.draw(f))?;
+        if let Event::Key(key) = event::read()? {
+            if app.handle_key(key) == Some(Request::Save) {
+                app.save(&mut before_save);
+            }
+        }
+    }
+    Ok(())
+}
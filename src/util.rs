@@ -0,0 +1,101 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use copy_dir::copy_dir;
+use tracing::debug;
+
+/// Name of the folder next to a save root where directories are staged
+/// before they replace one of the root.
+const STAGING: &str = ".yagde_staging";
+
+/// Writes through a temporary file in the same directory, renamed over
+/// `path` once complete, so a crash never leaves a truncated file behind.
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let tmp = path.with_file_name(format!(".{name}.tmp"));
+
+    let mut f = File::create(&tmp)?;
+    let written = f.write_all(data).and_then(|_| f.sync_all());
+    drop(f);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, path)
+}
+
+/// `$XDG_DATA_HOME/yagde`, or `~/.local/share/yagde`.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("yagde"))
+}
+
+/// Swaps the directory `to` for a copy of `from` without leaving it half
+/// written. The copy is staged next to the save root holding `to`, on the
+/// same file system but outside the folder the game and Steam Cloud look at,
+/// and whatever an interrupted swap left there is recovered first.
+pub fn replace_dir(from: &Path, to: &Path) -> io::Result<()> {
+    let name = to
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let root = to.parent().unwrap_or(Path::new("."));
+    let staging = staging_dir(root);
+    recover_staging(root)?;
+
+    let (new, old) = (
+        staging.join("new").join(&name),
+        staging.join("old").join(&name),
+    );
+    fs::create_dir_all(staging.join("new"))?;
+    fs::create_dir_all(staging.join("old"))?;
+    copy_dir(from, &new)?;
+    if to.exists() {
+        fs::rename(to, &old)?;
+    }
+    fs::rename(&new, to)?;
+    fs::remove_dir_all(&staging)?;
+    remove_if_empty(staging.parent())
+}
+
+/// Puts back the directories an interrupted `replace_dir` moved out of
+/// `root` and drops its half made copies.
+pub fn recover_staging(root: &Path) -> io::Result<()> {
+    let staging = staging_dir(root);
+    if !staging.exists() {
+        return Ok(());
+    }
+    let moved = staging.join("old");
+    for e in fs::read_dir(&moved).into_iter().flatten() {
+        let old = e?.path();
+        let to = root.join(old.file_name().unwrap_or_default());
+        if !to.exists() {
+            debug!(?to, "recovered from an interrupted swap");
+            fs::rename(&old, &to)?;
+        }
+    }
+    fs::remove_dir_all(&staging)?;
+    remove_if_empty(staging.parent())
+}
+
+// other roots of the same folder may still be staging
+fn remove_if_empty(dir: Option<&Path>) -> io::Result<()> {
+    match dir.map(fs::remove_dir) {
+        Some(Err(e)) if e.kind() != io::ErrorKind::DirectoryNotEmpty => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn staging_dir(root: &Path) -> PathBuf {
+    let name = root.file_name().unwrap_or_default().to_string_lossy();
+    let parent = root.parent().unwrap_or(Path::new("."));
+    parent.join(STAGING).join(name.as_ref())
+}
@@ -0,0 +1,88 @@
+use copy_dir::copy_dir;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+use yagde::backup::Backups;
+use yagde::util::write_atomic;
+
+/// A save root with `_TestMain`, and a store for its backups.
+fn root() -> (TempDir, TempDir) {
+    let tmp = TempDir::new().unwrap();
+    copy_dir("./tests/save/_TestMain", tmp.path().join("_TestMain")).unwrap();
+    (tmp, TempDir::new().unwrap())
+}
+
+#[test]
+fn rotation() {
+    let (root, store) = root();
+    let dir = root.path().join("_TestMain");
+    let mut backups = Backups::new(root.path());
+    backups.with_store(store.path()).with_keep(3);
+
+    let ids: Vec<String> = (0..5)
+        .map(|_| backups.create(&dir).unwrap().unwrap().id)
+        .collect();
+    let kept: Vec<String> = backups.list().unwrap().into_iter().map(|b| b.id).collect();
+    assert_eq!(kept, ids[2..]);
+
+    backups.with_keep(1);
+    assert_eq!(backups.prune().unwrap().len(), 2);
+    assert_eq!(backups.list().unwrap().len(), 1);
+
+    backups.with_keep(0);
+    assert!(backups.create(&dir).unwrap().is_none());
+}
+
+#[test]
+fn restore() {
+    let (root, store) = root();
+    let dir = root.path().join("_TestMain");
+    let file = dir.join("player.gdc");
+    let original = fs::read(&file).unwrap();
+
+    let mut backups = Backups::new(root.path());
+    backups.with_store(store.path());
+    let backup = backups.create(&dir).unwrap().unwrap();
+    write_atomic(&file, b"broken").unwrap();
+
+    backups.restore(&backup).unwrap();
+    assert_eq!(fs::read(&file).unwrap(), original);
+    // the broken save was backed up before being replaced
+    let last = backups.list().unwrap().pop().unwrap();
+    assert_eq!(fs::read(last.dir.join("player.gdc")).unwrap(), b"broken");
+    // nothing was left in or next to the root
+    assert_eq!(fs::read_dir(root.path()).unwrap().count(), 1);
+    let staging = root.path().parent().unwrap().join(".yagde_staging");
+    assert!(!staging.join(root.path().file_name().unwrap()).exists());
+}
+
+#[test]
+fn store_is_out_of_the_root() {
+    let (root, _) = root();
+    let backups = Backups::new(root.path());
+    assert!(!backups.store().unwrap().starts_with(root.path()));
+
+    // the same root however it's spelled
+    let other = root
+        .path()
+        .join("..")
+        .join(root.path().file_name().unwrap());
+    assert_eq!(Backups::new(&other).store(), backups.store());
+}
+
+#[test]
+fn atomic_write_leaves_no_temp_file() {
+    let tmp = TempDir::new().unwrap();
+    let file = tmp.path().join("player.gdc");
+    write_atomic(&file, b"one").unwrap();
+    write_atomic(&file, b"two").unwrap();
+    assert_eq!(fs::read(&file).unwrap(), b"two");
+    assert_eq!(
+        fs::read_dir(tmp.path()).unwrap().count(),
+        1,
+        "{:?}",
+        fs::read_dir(Path::new(tmp.path()))
+            .unwrap()
+            .collect::<Vec<_>>()
+    );
+}
@@ -1,25 +1,27 @@
-use yagde::gd::char;
-use std::fs::File;
+use std::fs::{self, File};
+use std::io::Cursor;
 use std::path::Path;
+use tempfile::TempDir;
+use yagde::gd::char;
+use yagde::gd::error::ErrorKind;
 
 #[test]
 fn save_as() {
     const PATH: &str = "./tests/save/_TestVanillaFemaleHC/player.gdc";
-    const NEW_PATH: &str = "./tests/save/_TestVanillaFemaleHC/test_clone.gdc";
 
     let mut current_char = char::Char::new();
     current_char.read(&Path::new(&PATH).to_path_buf()).unwrap();
 
+    let tmp = TempDir::new().unwrap();
+    let new_path = tmp.path().join("test_clone.gdc");
     let mut new_char = current_char.clone();
-    new_char
-        .save_as(&Path::new(&NEW_PATH).to_path_buf())
-        .unwrap();
+    new_char.save_as(&new_path).unwrap();
 
     let file1 = match File::open(PATH) {
         Ok(f) => f,
         Err(e) => panic!("{}", e),
     };
-    let file2 = match File::open(NEW_PATH) {
+    let file2 = match File::open(&new_path) {
         Ok(f) => f,
         Err(e) => panic!("{}", e),
     };
@@ -34,14 +36,14 @@ fn save_as() {
 #[test]
 fn rename() {
     const PATH: &str = "./tests/save/_TestVanillaFemaleHC/player.gdc";
-    const NEW_PATH: &str = "./tests/save/_TestVanillaFemaleHC/test_rename.gdc";
     const NEW_NAME: &str = "test_rename";
 
     let mut current_char = char::Char::new();
     current_char.read(&Path::new(&PATH).to_path_buf()).unwrap();
 
+    let tmp = TempDir::new().unwrap();
+    let new_path = &tmp.path().join("test_rename.gdc");
     let mut new_char = current_char.clone();
-    let new_path = &Path::new(&NEW_PATH).to_path_buf();
 
     new_char.rename(NEW_NAME).save_as(new_path).unwrap();
 
@@ -54,3 +56,139 @@ fn rename() {
 
     assert_eq!(new_char, current_char);
 }
+
+#[test]
+fn from_reader_to_writer() {
+    const PATH: &str = "./tests/save/_TestVanillaFemaleHC/player.gdc";
+
+    let data = fs::read(PATH).unwrap();
+    let current_char = char::Char::from_reader(Cursor::new(&data)).unwrap();
+
+    let mut out = Cursor::new(Vec::new());
+    current_char.to_writer(&mut out).unwrap();
+    let new_char = char::Char::from_reader(Cursor::new(out.get_ref())).unwrap();
+
+    assert_eq!(out.get_ref().len(), data.len());
+    assert_eq!(new_char, current_char);
+}
+
+#[test]
+fn latin1_roundtrip() {
+    const PATH: &str = "./tests/save/_TestMain/player.gdc";
+
+    let mut c = char::Char::from_reader(File::open(PATH).unwrap()).unwrap();
+    c.crucible.tokens_per_difficulty[0].push("caf\u{e9}".to_owned());
+
+    let mut out = Cursor::new(Vec::new());
+    c.to_writer(&mut out).unwrap();
+    // plain text save, so the byte is there as is
+    assert!(out.get_ref().windows(4).any(|w| w == b"caf\xe9"));
+
+    let read = char::Char::from_reader(Cursor::new(out.get_ref())).unwrap();
+    assert_eq!(read, c);
+
+    c.crucible.tokens_per_difficulty[0].push("\u{142}".to_owned());
+    let err = c.to_writer(Cursor::new(Vec::new())).unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::NotLatin1('\u{142}')));
+}
+
+#[test]
+fn write_with_source_seed() {
+    const PATH: &str = "./tests/save/_TestVanillaFemaleHC/player.gdc";
+
+    let data = fs::read(PATH).unwrap();
+    let current_char = char::Char::from_reader(Cursor::new(&data)).unwrap();
+    assert_ne!(current_char.seed(), 0);
+
+    let mut out = Vec::new();
+    current_char.to_writer(&mut out).unwrap();
+
+    assert_eq!(out, data);
+}
+
+#[test]
+fn write_with_seed() {
+    const PATH: &str = "./tests/save/_TestVanillaFemaleHC/player.gdc";
+
+    let data = fs::read(PATH).unwrap();
+    let current_char = char::Char::from_reader(Cursor::new(&data)).unwrap();
+
+    for seed in [0, 0x1234_5678] {
+        let mut new_char = current_char.clone();
+        let mut out = Vec::new();
+        new_char.with_seed(seed).to_writer(&mut out).unwrap();
+
+        assert_ne!(out, data);
+        let read_char = char::Char::from_reader(Cursor::new(&out)).unwrap();
+        assert_eq!(read_char.seed(), seed);
+        assert_eq!(read_char, new_char);
+    }
+
+    let mut new_char = current_char.clone();
+    let mut out = Vec::new();
+    new_char.with_random_seed().to_writer(&mut out).unwrap();
+    assert_ne!(new_char.seed(), 0);
+    assert_eq!(
+        char::Char::from_reader(Cursor::new(&out)).unwrap(),
+        new_char
+    );
+}
+
+#[test]
+fn rename_unicode() {
+    const PATH: &str = "./tests/save/_TestVanillaFemaleHC/player.gdc";
+
+    let data = fs::read(PATH).unwrap();
+    let current_char = char::Char::from_reader(Cursor::new(&data)).unwrap();
+
+    for name in ["Ярослава", "Élodie Müller", "Gå𝄞"] {
+        let mut new_char = current_char.clone();
+        let mut out = Vec::new();
+        new_char.rename(name).to_writer(&mut out).unwrap();
+
+        let read_char = char::Char::from_reader(Cursor::new(&out)).unwrap();
+        assert_eq!(read_char.header.name, name);
+        assert_eq!(read_char, new_char);
+    }
+}
+
+#[test]
+fn read_unpaired_surrogate() {
+    // plain text save: key, magic, header version, name length, name
+    const PATH: &str = "./tests/save/_TestMain/player.gdc";
+    const NAME_OFFSET: usize = 16;
+
+    let mut data = fs::read(PATH).unwrap();
+    data[NAME_OFFSET..NAME_OFFSET + 2].copy_from_slice(&0xd800_u16.to_le_bytes());
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    assert!(
+        err.to_string().contains("unpaired surrogate 0xd800"),
+        "{err}"
+    );
+    assert_eq!(err.offset(), Some(NAME_OFFSET as u64));
+
+    // the third unit, after two valid ones
+    let mut data = fs::read(PATH).unwrap();
+    let third = NAME_OFFSET + 4;
+    data[third..third + 2].copy_from_slice(&0xdc00_u16.to_le_bytes());
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    assert_eq!(err.offset(), Some(third as u64));
+}
+
+#[test]
+fn read_over_another_char() {
+    const MAIN: &str = "./tests/save/_TestMain/player.gdc";
+    const VANILLA: &str = "./tests/save/_TestVanillaFemaleHC/player.gdc";
+
+    let main = char::Char::from_reader(File::open(MAIN).unwrap()).unwrap();
+    let vanilla = char::Char::from_reader(File::open(VANILLA).unwrap()).unwrap();
+
+    let mut c = main.clone();
+    c.read_from(File::open(MAIN).unwrap()).unwrap();
+    assert_eq!(c, main);
+
+    c.read_from(File::open(VANILLA).unwrap()).unwrap();
+    assert_eq!(c, vanilla);
+}
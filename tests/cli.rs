@@ -0,0 +1,373 @@
+use copy_dir::copy_dir;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
+use yagde::gd::char;
+
+/// Copy of the fixture saves, so commands can change them.
+fn saves() -> TempDir {
+    let tmp = TempDir::new().unwrap();
+    for name in ["_TestMain", "_TestMain121"] {
+        copy_dir(Path::new("./tests/save").join(name), tmp.path().join(name)).unwrap();
+    }
+    tmp
+}
+
+fn yagde(saves: &TempDir, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_yagde"))
+        .arg("-s")
+        .arg(saves.path())
+        .args(args)
+        .env("XDG_CONFIG_HOME", saves.path().join("config"))
+        .env("XDG_DATA_HOME", saves.path().join("data"))
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+fn read(saves: &TempDir, dir: &str) -> char::Char {
+    let mut c = char::Char::new();
+    let file: PathBuf = saves.path().join(dir).join("player.gdc");
+    c.read(&file).unwrap();
+    c
+}
+
+#[test]
+fn view_by_name_or_dir() {
+    let saves = saves();
+
+    let out = yagde(&saves, &["view", "TestMain"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("TestMain"));
+
+    let dir = saves.path().join("_TestMain121");
+    let out = yagde(&saves, &["view", dir.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("TestOneTwoOne"));
+
+    assert_eq!(yagde(&saves, &["view", "_TestMain"]).status.code(), Some(0));
+}
+
+#[test]
+fn unknown_character() {
+    let saves = saves();
+    assert_eq!(yagde(&saves, &["view", "Nobody"]).status.code(), Some(3));
+}
+
+#[test]
+fn changes_need_confirmation() {
+    let saves = saves();
+
+    let out = yagde(&saves, &["rename", "TestMain", "Renamed"]);
+    assert_eq!(out.status.code(), Some(5));
+    assert_eq!(read(&saves, "_TestMain").header.name, "TestMain");
+
+    let out = yagde(&saves, &["--yes", "rename", "TestMain", "Renamed"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(read(&saves, "_TestMain").header.name, "Renamed");
+}
+
+#[test]
+fn reset_needs_a_part() {
+    let saves = saves();
+    assert_eq!(yagde(&saves, &["reset", "TestMain"]).status.code(), Some(2));
+
+    let out = yagde(&saves, &["reset", "TestMain", "--deaths", "--skills", "-y"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(read(&saves, "_TestMain").stats.deaths, 0);
+}
+
+#[test]
+fn add_money() {
+    let saves = saves();
+    let before = read(&saves, "_TestMain").info.money;
+
+    let out = yagde(&saves, &["add-money", "TestMain", "1000", "-y"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(read(&saves, "_TestMain").info.money, before + 1000);
+}
+
+#[test]
+fn boost_factions() {
+    let saves = saves();
+
+    let out = yagde(&saves, &["boost-factions", "TestMain", "hostile", "-y"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert!(read(&saves, "_TestMain")
+        .factions
+        .factions
+        .iter()
+        .all(|f| f.value >= 0.0 || f.value == -20000.0));
+}
+
+#[test]
+fn clone() {
+    let saves = saves();
+
+    let out = yagde(&saves, &["clone", "TestMain", "Copy"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(read(&saves, "_Copy").header.name, "Copy");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_output() {
+    let saves = saves();
+
+    let out = yagde(&saves, &["view", "TestMain", "--output", "json"]);
+    assert_eq!(out.status.code(), Some(0));
+    let view: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(view["header"]["name"], "TestMain");
+    assert_eq!(view["bio"]["physique"], 258.0);
+    assert!(view["skills"]["skills"].as_array().unwrap().len() > 3);
+    assert!(view["inventory"]["equipped"].as_u64().unwrap() > 0);
+
+    let out = yagde(&saves, &["list", "--output", "json"]);
+    assert_eq!(out.status.code(), Some(0));
+    let list: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let names: Vec<&str> = list
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["TestMain", "TestOneTwoOne"]);
+}
+
+#[test]
+fn diff() {
+    let saves = saves();
+    let out = yagde(&saves, &["diff", "TestMain", "TestMain"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "No changes\n");
+
+    let out = yagde(&saves, &["diff", "TestMain", "TestOneTwoOne"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&out.stdout)
+        .contains("Header.name: \"TestMain\" -> \"TestOneTwoOne\""));
+}
+
+#[test]
+fn backup_before_write() {
+    let saves = saves();
+    assert_eq!(
+        yagde(&saves, &["-y", "add-money", "TestMain", "5"])
+            .status
+            .code(),
+        Some(0)
+    );
+    let out = yagde(&saves, &["backup", "list"]);
+    let id = String::from_utf8_lossy(&out.stdout).trim().to_owned();
+    assert!(id.starts_with("_TestMain/"), "{id}");
+
+    let restore = yagde(&saves, &["-y", "backup", "restore", &id]);
+    assert_eq!(restore.status.code(), Some(0));
+    assert_eq!(read(&saves, "_TestMain").info.money, 94717);
+
+    let missing = yagde(&saves, &["-y", "backup", "restore", "_TestMain/1"]);
+    assert_eq!(missing.status.code(), Some(3));
+}
+
+#[test]
+fn dry_run() {
+    let saves = saves();
+    let file = saves.path().join("_TestMain").join("player.gdc");
+    let before = std::fs::read(&file).unwrap();
+
+    let out = yagde(&saves, &["--dry-run", "-y", "reset", "TestMain", "--all"]);
+    assert_eq!(out.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains("Bio.skill_points: 0 -> 85 (+85)"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Dry run, nothing written"));
+    assert_eq!(std::fs::read(&file).unwrap(), before);
+
+    // the preview comes before the confirmation
+    let out = yagde(&saves, &["add-money", "TestMain", "5"]);
+    assert_eq!(out.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Info.money: 94717 -> 94722 (+5)"));
+    assert_eq!(std::fs::read(&file).unwrap(), before);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn config() {
+    let saves = saves();
+    let out = yagde(&saves, &["config", "set", "output", "json"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "output = json\n");
+    let out = yagde(&saves, &["config", "get", "output"]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "json\n");
+
+    // the configured output is the default
+    let out = yagde(&saves, &["view", "TestMain"]);
+    let view: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(view["header"]["name"], "TestMain");
+
+    let out = yagde(&saves, &["config", "set", "output", "yaml"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert_eq!(
+        yagde(&saves, &["config", "get", "colour"]).status.code(),
+        Some(2)
+    );
+
+    yagde(&saves, &["config", "set", "keep_backups", "0"]);
+    yagde(&saves, &["-y", "add-money", "TestMain", "5"]);
+    assert!(yagde(&saves, &["backup", "list"]).stdout.is_empty());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn protected_character() {
+    let saves = saves();
+    yagde(&saves, &["config", "set", "protected", "TestMain,_Other"]);
+
+    let file = saves.path().join("_TestMain").join("player.gdc");
+    let before = std::fs::read(&file).unwrap();
+    let out = yagde(&saves, &["-y", "add-money", "TestMain", "5"]);
+    assert_eq!(out.status.code(), Some(6));
+    assert_eq!(std::fs::read(&file).unwrap(), before);
+
+    assert_eq!(
+        yagde(&saves, &["clone", "TestOneTwoOne", "Other"])
+            .status
+            .code(),
+        Some(6)
+    );
+    assert_eq!(
+        yagde(&saves, &["-y", "add-money", "TestOneTwoOne", "5"])
+            .status
+            .code(),
+        Some(0)
+    );
+}
+
+#[test]
+fn clone_to_mod_folder() {
+    let saves = TempDir::new().unwrap();
+    let main = saves.path().join("save/main");
+    std::fs::create_dir_all(&main).unwrap();
+    copy_dir("./tests/save/_TestMain", main.join("_TestMain")).unwrap();
+
+    let out = Command::new(env!("CARGO_BIN_EXE_yagde"))
+        .arg("-s")
+        .arg(&main)
+        .args(["clone", "TestMain", "--to", "gdx2"])
+        .env("XDG_CONFIG_HOME", saves.path().join("config"))
+        .env("XDG_DATA_HOME", saves.path().join("data"))
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(0));
+    let copy = saves.path().join("save/user/gdx2/_TestMain/player.gdc");
+    assert!(copy.is_file());
+
+    let out = Command::new(env!("CARGO_BIN_EXE_yagde"))
+        .arg("-s")
+        .arg(&main)
+        .arg("list")
+        .env("XDG_CONFIG_HOME", saves.path().join("config"))
+        .env("XDG_DATA_HOME", saves.path().join("data"))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains("TestMain (lvl 28, HC, Crucible, mod gdx2)"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn sync() {
+    let saves = saves();
+    let cloud = TempDir::new().unwrap();
+    copy_dir("./tests/save/_TestMain", cloud.path().join("_TestMain")).unwrap();
+    let cloud = cloud.path().to_str().unwrap();
+    let local = saves.path().to_str().unwrap();
+
+    let out = yagde(&saves, &["sync", "--local", local, "--cloud", cloud]);
+    assert_eq!(out.status.code(), Some(5));
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains("  cloud: missing\n  local is newer"),
+        "{stdout}"
+    );
+
+    let out = yagde(&saves, &["-y", "sync", "--local", local, "--cloud", cloud]);
+    assert_eq!(out.status.code(), Some(0));
+    let out = yagde(&saves, &["sync", "--local", local, "--cloud", cloud]);
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Everything is in sync"));
+}
+
+#[test]
+fn list_with_broken_save() {
+    let saves = saves();
+    let broken = saves.path().join("_Broken");
+    std::fs::create_dir(&broken).unwrap();
+    std::fs::write(broken.join("player.gdc"), b"garbage").unwrap();
+
+    let out = yagde(&saves, &["list"]);
+    assert_eq!(out.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("TestOneTwoOne"), "{stdout}");
+    assert!(stdout.contains("_Broken (corrupt at offset"), "{stdout}");
+
+    assert_eq!(yagde(&saves, &["view", "_Broken"]).status.code(), Some(4));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn sync_conflict() {
+    let saves = saves();
+    let cloud = TempDir::new().unwrap();
+    let file = cloud.path().join("_TestMain/player.gdc");
+    copy_dir("./tests/save/_TestMain", cloud.path().join("_TestMain")).unwrap();
+    let mut c = read(&saves, "_TestMain");
+    c.add_money(1);
+    c.save_as(&file).unwrap();
+    let modified = std::fs::metadata(saves.path().join("_TestMain/player.gdc"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    let cloud = cloud.path().to_str().unwrap();
+    let local = saves.path().to_str().unwrap();
+
+    let out = yagde(&saves, &["-y", "sync", "--local", local, "--cloud", cloud]);
+    assert_eq!(out.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("  conflict, both differ"), "{stdout}");
+    assert!(stdout.contains("1 characters conflict"), "{stdout}");
+    let out = yagde(&saves, &["sync", "--local", local, "--cloud", cloud]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(!stdout.contains("Everything is in sync"), "{stdout}");
+    assert_eq!(read(&saves, "_TestMain").info.money, 94717);
+
+    // keeping the cloud copy overwrites a protected local character
+    yagde(&saves, &["config", "set", "protected", "TestMain"]);
+    let out = yagde(
+        &saves,
+        &[
+            "-y", "sync", "--local", local, "--cloud", cloud, "--prefer", "cloud",
+        ],
+    );
+    assert_eq!(out.status.code(), Some(6));
+    assert_eq!(read(&saves, "_TestMain").info.money, 94717);
+
+    yagde(&saves, &["config", "set", "protected", ""]);
+    let out = yagde(
+        &saves,
+        &[
+            "-y", "sync", "--local", local, "--cloud", cloud, "--prefer", "cloud",
+        ],
+    );
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(read(&saves, "_TestMain").info.money, 94718);
+}
@@ -0,0 +1,18 @@
+use std::fs;
+
+/// Plain text TestMain with the Stats block version bumped to 12. Returns
+/// the data and the offset of the Stats block.
+pub fn bumped_stats() -> (Vec<u8>, usize) {
+    const PATH: &str = "./tests/save/_TestMain/player.gdc";
+    const STATS: [u8; 4] = 16_u32.to_le_bytes();
+    const VERSION: [u8; 4] = 11_u32.to_le_bytes();
+
+    let mut data = fs::read(PATH).unwrap();
+    let start = data
+        .windows(12)
+        .position(|w| w[..4] == STATS && w[8..] == VERSION)
+        .unwrap();
+    data[start + 8..start + 12].copy_from_slice(&12_u32.to_le_bytes());
+
+    (data, start)
+}
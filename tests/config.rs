@@ -0,0 +1,76 @@
+use std::path::Path;
+use yagde::config::{expand_home, Config, ConfigError};
+
+#[test]
+fn get_and_set() {
+    let mut config = Config::default();
+    assert_eq!(config.get("keep_backups").unwrap(), "");
+
+    config.set("keep_backups", "3").unwrap();
+    config.set("steam_account", "12345").unwrap();
+    config.set("save_roots", "~/a, /b ,").unwrap();
+    assert_eq!(config.keep_backups, Some(3));
+    assert_eq!(config.get("save_roots").unwrap(), "~/a,/b");
+
+    config.set("keep_backups", "").unwrap();
+    assert_eq!(config.keep_backups, None);
+
+    assert!(matches!(
+        config.set("steam_account", "bob"),
+        Err(ConfigError::InvalidValue { .. })
+    ));
+    assert!(matches!(
+        config.set("output", "xml"),
+        Err(ConfigError::InvalidValue { .. })
+    ));
+    assert!(matches!(
+        config.get("nope"),
+        Err(ConfigError::UnknownKey(_))
+    ));
+}
+
+#[test]
+fn protected() {
+    let mut config = Config::default();
+    config.set("protected", "Bob,_Alice").unwrap();
+    assert!(config.is_protected(Path::new("/saves/_Whoever"), "Bob"));
+    assert!(config.is_protected(Path::new("/saves/_Alice"), "Renamed"));
+    assert!(!config.is_protected(Path::new("/saves/_Carol"), "Carol"));
+}
+
+#[test]
+fn home_expansion() {
+    let home = std::env::var("HOME").unwrap();
+    assert_eq!(expand_home("~/saves"), Path::new(&home).join("saves"));
+    assert_eq!(expand_home("$HOME/saves"), Path::new(&home).join("saves"));
+    assert_eq!(expand_home("~bob/saves"), Path::new("~bob/saves"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn load_and_save() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let path = tmp.path().join("yagde").join("config.toml");
+    assert_eq!(Config::load(&path).unwrap(), Config::default());
+
+    let mut config = Config::default();
+    config.set("protected", "Bob").unwrap();
+    config.set("output", "json").unwrap();
+    config.save(&path).unwrap();
+    assert_eq!(Config::load(&path).unwrap(), config);
+
+    std::fs::write(&path, "colour = \"red\"\n").unwrap();
+    assert!(matches!(Config::load(&path), Err(ConfigError::Parse(_))));
+}
+
+#[cfg(not(feature = "serde"))]
+#[test]
+fn load_without_serde() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let path = tmp.path().join("config.toml");
+    assert_eq!(Config::load(&path).unwrap(), Config::default());
+
+    // a file that can't be parsed must not pass for an empty config
+    std::fs::write(&path, "protected = [\"Bob\"]\n").unwrap();
+    assert!(matches!(Config::load(&path), Err(ConfigError::Unsupported)));
+}
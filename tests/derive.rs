@@ -0,0 +1,57 @@
+use std::fs;
+use std::io::Cursor;
+use yagde::gd::char;
+use yagde::gd::explain::explain;
+
+const PATH: &str = "./tests/save/_TestMain/player.gdc";
+
+#[test]
+fn crucible_tokens_roundtrip() {
+    let mut c = char::Char::from_reader(Cursor::new(fs::read(PATH).unwrap())).unwrap();
+    c.crucible.tokens_per_difficulty[1].push("token_a".to_owned());
+
+    let mut data = Vec::new();
+    c.to_writer(Cursor::new(&mut data)).unwrap();
+    let read = char::Char::from_reader(Cursor::new(&data)).unwrap();
+    assert_eq!(read.crucible, c.crucible);
+
+    let e = explain(&data);
+    assert!(e
+        .fields
+        .iter()
+        .any(|f| f.name == "Crucible.tokens_per_difficulty[1][0]"));
+}
+
+#[test]
+fn version_gated_fields() {
+    let mut data = fs::read(PATH).unwrap();
+    let fields = explain(&data).fields;
+    let offset = |name: &str| {
+        fields
+            .iter()
+            .find(|f| f.name.starts_with(name))
+            .unwrap()
+            .offset as usize
+    };
+
+    // turn the version 11 stats into version 9 ones, which end right after
+    // the survival mode counters
+    let block = offset("Stats");
+    let version = offset("Stats.version");
+    let from = offset("Stats.skill_map");
+    let to = offset("Stats.unknown1");
+    data.drain(from..to);
+    let len = u32::from_le_bytes(data[block + 4..block + 8].try_into().unwrap());
+    data[block + 4..block + 8].copy_from_slice(&(len - (to - from) as u32).to_le_bytes());
+    data[version..version + 4].copy_from_slice(&9_u32.to_le_bytes());
+
+    let e = explain(&data);
+    assert!(e.error.is_none(), "{e}");
+    assert!(e
+        .fields
+        .iter()
+        .any(|f| f.name == "Stats.survival_powerups_activated"));
+    assert!(!e.fields.iter().any(|f| f.name.starts_with("Stats.endless")));
+
+    char::Char::verify_roundtrip(&data).unwrap();
+}
@@ -0,0 +1,215 @@
+use std::fs;
+use std::io::Cursor;
+use yagde::gd::char;
+use yagde::gd::diff::{Change, Place, Scalar};
+
+const PATH: &str = "./tests/save/_TestMain/player.gdc";
+
+fn read() -> char::Char {
+    char::Char::from_reader(Cursor::new(fs::read(PATH).unwrap())).unwrap()
+}
+
+#[test]
+fn same_save() {
+    let c = read();
+    assert!(c.diff(&c.clone()).is_empty());
+}
+
+#[test]
+fn edits() {
+    let old = read();
+    let mut new = old.clone();
+    new.add_money(100).boost_frendly_factions().reset_skills();
+
+    let changes = old.diff(&new);
+    assert_eq!(
+        changes[0],
+        Change::Value {
+            field: "Info.money",
+            old: Scalar::Int(94717),
+            new: Scalar::Int(94817),
+        }
+    );
+    assert_eq!(changes[0].to_string(), "Info.money: 94717 -> 94817 (+100)");
+    assert!(changes.iter().any(|c| matches!(
+        c,
+        Change::Value {
+            field: "Bio.skill_points",
+            ..
+        }
+    )));
+    assert!(changes.iter().any(
+        |c| matches!(c, Change::SkillRemoved { name, .. } if name.starts_with("records/skills/playerclass"))
+    ));
+    assert!(changes
+        .iter()
+        .any(|c| matches!(c, Change::Faction { new, .. } if *new == 25000.0)));
+    assert!(!changes.iter().any(|c| matches!(
+        c,
+        Change::ItemAdded { .. } | Change::ItemRemoved { .. } | Change::ItemMoved { .. }
+    )));
+}
+
+#[test]
+fn other_character() {
+    let old = read();
+    let data = fs::read("./tests/save/_TestMain121/player.gdc").unwrap();
+    let new = char::Char::from_reader(Cursor::new(data)).unwrap();
+
+    let changes = old.diff(&new);
+    assert!(changes.iter().any(|c| matches!(
+        c,
+        Change::Value { field: "Header.name", new: Scalar::Text(n), .. } if n == "TestOneTwoOne"
+    )));
+    assert!(changes
+        .iter()
+        .any(|c| matches!(c, Change::SkillLevel { .. })));
+    assert!(changes.iter().any(|c| matches!(
+        c,
+        Change::ItemAdded {
+            to: Place::Equipment { .. },
+            ..
+        }
+    )));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn moved_item() {
+    let old = read();
+    let mut json = serde_json::to_value(&old).unwrap();
+    let item = &mut json["inventory"]["sacks"][0]["items"][0];
+    let (x, y) = (item["x"].as_u64().unwrap(), item["y"].as_u64().unwrap());
+    item["x"] = (x + 100).into();
+    let new: char::Char = serde_json::from_value(json).unwrap();
+
+    let changes = old.diff(&new);
+    assert_eq!(changes.len(), 1);
+    match &changes[0] {
+        Change::ItemMoved { from, to, .. } => {
+            assert_eq!(
+                *from,
+                Place::Sack {
+                    sack: 0,
+                    x: x as u32,
+                    y: y as u32
+                }
+            );
+            assert_eq!(
+                *to,
+                Place::Sack {
+                    sack: 0,
+                    x: x as u32 + 100,
+                    y: y as u32
+                }
+            );
+        }
+        c => panic!("unexpected change {c}"),
+    }
+}
+
+/// Diffs the save against itself with one field changed through the JSON
+/// export, which reaches the private fields too.
+#[cfg(feature = "serde")]
+fn edited(edit: impl FnOnce(&mut serde_json::Value)) -> Vec<Change> {
+    let old = read();
+    let mut json = serde_json::to_value(&old).unwrap();
+    edit(&mut json);
+    let new: char::Char = serde_json::from_value(json).unwrap();
+    old.diff(&new)
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn blocks_without_values() {
+    use serde_json::json;
+
+    let uid = json!([1_u8; 16].to_vec());
+    let cases = [
+        ("RespawnList", "/respawns/spawns/1", uid.clone()),
+        ("TeleportList", "/teleports/uids/2", json!([uid])),
+        ("MarkerList", "/markers/uids/0", json!([uid])),
+        ("ShrineList", "/shrines/uids/0/0", uid.clone()),
+        (
+            "NoteList",
+            "/notes/notes/0",
+            json!("records/items/loreobjects/other.dbr"),
+        ),
+        ("UI", "/ui/camera_distance", json!(20.0)),
+        ("TutorialPages", "/tutorials/pages/0", json!(99)),
+        (
+            "Crucible",
+            "/crucible/tokens_per_difficulty/0",
+            json!(["records/crucible/token.dbr"]),
+        ),
+    ];
+
+    for (block, field, value) in cases {
+        let changes = edited(|j| *j.pointer_mut(field).unwrap() = value);
+        assert_eq!(changes, [Change::Block { block }], "{field}");
+        assert_eq!(changes[0].to_string(), format!("{block} changed"));
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn skill_fields() {
+    let changes = edited(|j| {
+        let skill = &mut j["skills"]["skills"][0];
+        skill["enabled"] = 0.into();
+        skill["auto_cast_skill"] = "records/skills/other.dbr".into();
+    });
+    let name = "records/skills/default/defaultkickattack.dbr";
+    assert_eq!(
+        changes,
+        [
+            Change::SkillField {
+                name: name.to_owned(),
+                field: "enabled",
+                old: Scalar::Int(1),
+                new: Scalar::Int(0),
+            },
+            Change::SkillField {
+                name: name.to_owned(),
+                field: "auto_cast_skill",
+                old: Scalar::Text(String::new()),
+                new: Scalar::Text("records/skills/other.dbr".to_owned()),
+            },
+        ]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        format!("skill {name}: enabled 1 -> 0")
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn item_fields() {
+    let changes = edited(|j| {
+        let item = &mut j["inventory"]["sacks"][0]["items"][0]["item"];
+        item["seed"] = 1.into();
+        item["stack_count"] = 79.into();
+        item["component_id"] = "records/items/materia/compa_ancientarmorplate.dbr".into();
+    });
+    let at = Place::Sack {
+        sack: 0,
+        x: 0,
+        y: 1,
+    };
+    let fields: Vec<_> = changes
+        .iter()
+        .map(|c| match c {
+            Change::ItemChanged {
+                at: a, field, item, ..
+            } => {
+                assert_eq!(*a, at);
+                assert_eq!(item, "records/items/misc/potions/potion_healtha01.dbr");
+                *field
+            }
+            c => panic!("unexpected change {c}"),
+        })
+        .collect();
+    assert_eq!(fields, ["seed", "component_id", "stack_count"]);
+    assert!(changes[2].to_string().ends_with("stack_count 78 -> 79"));
+}
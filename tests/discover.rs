@@ -0,0 +1,158 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use yagde::discover::{game_root, library_folders, mod_name, Discovery, Source};
+
+const SAVES: &str = "Documents/My Games/Grim Dawn/save/main";
+
+/// Creates a save root with `chars` character directories in a prefix.
+fn saves_in(prefix: &Path, user: &str, chars: usize) -> PathBuf {
+    let root = prefix.join("drive_c/users").join(user).join(SAVES);
+    for i in 0..chars {
+        fs::create_dir_all(root.join(format!("_Char{i}"))).unwrap();
+    }
+    fs::create_dir_all(&root).unwrap();
+    root
+}
+
+fn write(path: &Path, text: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+#[test]
+fn every_layout() {
+    let home = TempDir::new().unwrap();
+    let home = home.path();
+    let elsewhere = TempDir::new().unwrap();
+
+    // Steam, with a second library and a cloud account
+    let steam = home.join(".local/share/Steam");
+    let library = elsewhere.path().join("SteamLibrary");
+    write(
+        &steam.join("steamapps/libraryfolders.vdf"),
+        &format!(
+            "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+            steam.display(),
+            library.display()
+        ),
+    );
+    let proton = saves_in(
+        &library.join("steamapps/compatdata/219990/pfx"),
+        "steamuser",
+        2,
+    );
+    let cloud = steam.join("userdata/4242/219990/remote/save/main");
+    fs::create_dir_all(cloud.join("_Cloud")).unwrap();
+    let cloud_mod = steam.join("userdata/4242/219990/remote/save/user/gdx2");
+    fs::create_dir_all(cloud_mod.join("_Modded")).unwrap();
+    fs::create_dir_all(steam.join("userdata/99/0/remote")).unwrap();
+
+    // Lutris, from its game config
+    let lutris_prefix = elsewhere.path().join("lutris-gd");
+    let lutris = saves_in(&lutris_prefix, "me", 1);
+    write(
+        &home.join(".config/lutris/games/grim-dawn-123.yml"),
+        &format!(
+            "game:\n  exe: GrimDawn.exe\n  prefix: {}\n",
+            lutris_prefix.display()
+        ),
+    );
+
+    // Heroic, one from its config and one default prefix
+    let heroic_prefix = elsewhere.path().join("heroic-gd");
+    let heroic = saves_in(&heroic_prefix, "me", 1);
+    write(
+        &home.join(".config/heroic/GamesConfig/abc.json"),
+        &format!(
+            "{{\n  \"abc\": {{\n    \"winePrefix\": \"{}\"\n  }}\n}}\n",
+            heroic_prefix.display()
+        ),
+    );
+    let heroic_default = saves_in(
+        &home.join("Games/Heroic/Prefixes/default/Grim Dawn"),
+        "me",
+        3,
+    );
+
+    // Bottles, Flatpak
+    let bottles = saves_in(
+        &home.join(".var/app/com.usebottles.bottles/data/bottles/bottles/GD"),
+        "me",
+        1,
+    );
+
+    // WINEPREFIX
+    let wine_prefix = elsewhere.path().join("wine");
+    let wine = saves_in(&wine_prefix, "me", 1);
+
+    let mut discovery = Discovery::new(home);
+    discovery.with_wine_prefix(&wine_prefix);
+    let roots = discovery.roots();
+    let paths: Vec<&Path> = roots.iter().map(|r| r.path.as_path()).collect();
+    assert_eq!(
+        paths,
+        [
+            cloud.as_path(),
+            &cloud_mod,
+            &proton,
+            &lutris,
+            &heroic,
+            &heroic_default,
+            &bottles,
+            &wine
+        ]
+    );
+
+    assert_eq!(
+        roots[0].source,
+        Source::SteamCloud {
+            account: "4242".to_owned()
+        }
+    );
+    assert_eq!(roots[0].label(), "Steam Cloud account 4242 (1 chars)");
+    assert_eq!(roots[1].mod_name.as_deref(), Some("gdx2"));
+    assert_eq!(
+        roots[1].label(),
+        "Steam Cloud account 4242, mod gdx2 (1 chars)"
+    );
+    assert_eq!(roots[2].source, Source::Steam { library });
+    assert_eq!(roots[2].chars, 2);
+    assert!(matches!(roots[3].source, Source::Lutris { .. }));
+    assert!(matches!(roots[5].source, Source::Heroic { .. }));
+    assert_eq!(
+        roots[6].source,
+        Source::Bottles {
+            bottle: "GD".to_owned()
+        }
+    );
+}
+
+#[test]
+fn nothing_installed() {
+    let home = TempDir::new().unwrap();
+    // a Steam without userdata or Grim Dawn is skipped, not an error
+    fs::create_dir_all(home.path().join(".steam/steam/steamapps")).unwrap();
+    assert!(Discovery::new(home.path()).roots().is_empty());
+}
+
+#[test]
+fn vdf_paths() {
+    let vdf = "\"libraryfolders\"\n{\n\t\"0\"\n\t{\n\t\t\"path\"\t\t\"D:\\\\Games\\\\Steam\"\n\t\t\"label\"\t\t\"\"\n\t}\n}\n";
+    assert_eq!(library_folders(vdf), [PathBuf::from("D:\\Games\\Steam")]);
+}
+
+#[test]
+fn mod_roots_of_an_install() {
+    let save = Path::new("/pfx/save");
+    assert_eq!(mod_name(&save.join("user/gdx2")).as_deref(), Some("gdx2"));
+    assert_eq!(mod_name(&save.join("main")), None);
+    assert_eq!(
+        game_root(&save.join("main"), Some("gdx2")),
+        Some(save.join("user/gdx2"))
+    );
+    assert_eq!(
+        game_root(&save.join("user/gdx2"), None),
+        Some(save.join("main"))
+    );
+}
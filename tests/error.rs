@@ -0,0 +1,89 @@
+mod common;
+
+use common::bumped_stats;
+use std::fs;
+use std::io::Cursor;
+use tempfile::TempDir;
+use yagde::gd::char;
+use yagde::gd::error::ErrorKind;
+
+const PATH: &str = "./tests/save/_TestMain/player.gdc";
+
+#[test]
+fn unsupported_version_location() {
+    let (data, start) = bumped_stats();
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::UnsupportedVersion { actual: 12, .. }
+    ));
+    assert_eq!(err.offset(), Some(start as u64 + 8));
+    assert_eq!(err.block_seq(), Some(16));
+    assert_eq!(err.path(), "Stats");
+}
+
+#[test]
+fn nested_location() {
+    let mut data = fs::read(PATH).unwrap();
+    // length of the second item's base name in the first sack
+    let first = data
+        .windows(13)
+        .position(|w| w == b"records/items")
+        .unwrap();
+    let second = first
+        + 13
+        + data[first + 13..]
+            .windows(13)
+            .position(|w| w == b"records/items")
+            .unwrap();
+    data[second - 4..second].copy_from_slice(&0x00ff_ffff_u32.to_le_bytes());
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::LimitExceeded {
+            what: "string length",
+            actual: 0x00ff_ffff,
+            ..
+        }
+    ));
+    assert_eq!(err.offset(), Some(second as u64 - 4));
+    assert_eq!(err.block_seq(), Some(3));
+    assert_eq!(err.path(), "Inventory > sack 0 > item 1");
+    assert!(err
+        .to_string()
+        .ends_with("in Inventory > sack 0 > item 1 (block 3)"));
+}
+
+#[test]
+fn header_location() {
+    const SEX_OFFSET: usize = 0x20;
+
+    let mut data = fs::read(PATH).unwrap();
+    assert_eq!(data[SEX_OFFSET], 1);
+    data[SEX_OFFSET] = 7;
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::InvalidValue {
+            what: "sex",
+            actual: 7
+        }
+    ));
+    assert_eq!(err.block_seq(), None);
+    assert_eq!(err.path(), "Header");
+}
+
+#[test]
+fn io_error_has_no_offset() {
+    let tmp = TempDir::new().unwrap();
+
+    let err = char::Char::new()
+        .read(&tmp.path().join("missing.gdc"))
+        .unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::Io(_)));
+    assert_eq!(err.offset(), None);
+    assert!(!err.to_string().contains("offset"), "{err}");
+}
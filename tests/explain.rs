@@ -0,0 +1,54 @@
+use std::fs;
+use yagde::gd::explain::explain;
+use yagde::gd::Value;
+
+const PATH: &str = "./tests/save/_TestMain/player.gdc";
+
+#[test]
+fn explain_fields() {
+    let data = fs::read(PATH).unwrap();
+    let e = explain(&data);
+    assert!(e.error.is_none());
+
+    let physique = e.fields.iter().find(|f| f.name == "Bio.physique").unwrap();
+    assert_eq!(physique.value, Value::F32(258.0));
+    assert!(physique
+        .to_string()
+        .starts_with("0x00e3 Bio.physique f32 258.0 "));
+
+    let stats = e.fields.iter().find(|f| f.name == "Stats").unwrap();
+    assert!(matches!(stats.value, Value::BlockStart { seq: 16, .. }));
+}
+
+#[test]
+fn explain_covers_every_byte() {
+    for entry in fs::read_dir("./tests/save").unwrap() {
+        let data = fs::read(entry.unwrap().path().join("player.gdc")).unwrap();
+        let e = explain(&data);
+        assert!(e.error.is_none());
+
+        let mut next = 0;
+        for f in e.fields.iter() {
+            assert_eq!(f.offset, next, "{f}");
+            assert_eq!(&data[f.offset as usize..][..f.raw.len()], &f.raw[..]);
+            next += f.raw.len() as u64;
+        }
+        assert_eq!(next, data.len() as u64);
+    }
+}
+
+#[test]
+fn explain_stops_at_error() {
+    const BIO_VERSION: usize = 0x00c7;
+
+    let mut data = fs::read(PATH).unwrap();
+    data[BIO_VERSION] = 9;
+
+    let e = explain(&data);
+    assert!(e.error.is_some());
+    assert_eq!(e.fields.last().unwrap().name, "Bio.version");
+    let unread = data.len() - BIO_VERSION - 4;
+    assert!(e
+        .to_string()
+        .ends_with(&format!("{unread} of {} bytes left unread\n", data.len())));
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "serde")]
+
+use std::fs;
+use std::io::Cursor;
+use yagde::gd::char;
+
+fn each_fixture(roundtrip: impl Fn(&char::Char) -> char::Char) {
+    for entry in fs::read_dir("./tests/save").unwrap() {
+        let path = entry.unwrap().path().join("player.gdc");
+        let data = fs::read(&path).unwrap();
+        let c = char::Char::from_reader(Cursor::new(&data)).unwrap();
+
+        let mut out = Vec::new();
+        roundtrip(&c).to_writer(&mut out).unwrap();
+        assert!(out == data, "{} changed", path.display());
+    }
+}
+
+#[test]
+fn json_roundtrip() {
+    each_fixture(|c| serde_json::from_str(&serde_json::to_string(c).unwrap()).unwrap());
+}
+
+#[test]
+fn toml_roundtrip() {
+    each_fixture(|c| toml::from_str(&toml::to_string(c).unwrap()).unwrap());
+}
+
+#[test]
+fn stash_pages_follow_the_list() {
+    let data = fs::read("./tests/save/_TestMain/player.gdc").unwrap();
+    let c = char::Char::from_reader(Cursor::new(&data)).unwrap();
+
+    let mut v = serde_json::to_value(&c).unwrap();
+    assert!(v["stash"].get("num_pages").is_none());
+    let pages = v["stash"]["pages"].as_array_mut().unwrap();
+    pages.push(pages[0].clone());
+
+    let edited: char::Char = serde_json::from_value(v).unwrap();
+    let mut out = Vec::new();
+    edited.to_writer(&mut out).unwrap();
+    let read = char::Char::from_reader(Cursor::new(&out)).unwrap();
+    assert_eq!(read.stash.page_count(), c.stash.page_count() + 1);
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use common::bumped_stats;
+use std::fs;
+use std::path::Path;
+use yagde::gd::char;
+use yagde::gd::error::{Error, ErrorKind, Location};
+use yagde::index::{Health, SaveIndex};
+
+#[test]
+fn scan_fixtures() {
+    let mut index = SaveIndex::new(Path::new("./tests/save"));
+    let chars = index.scan().unwrap();
+
+    assert_eq!(chars.len(), 8);
+    let main = chars.iter().find(|c| c.header.name == "TestMain").unwrap();
+    assert_eq!(main.header.level, 28);
+    assert_eq!(main.header.hardcore, 1);
+    assert!(main.dir.ends_with("_TestMain"));
+    assert_eq!(main.label(), "TestMain (lvl 28, HC, Crucible)");
+
+    // second scan is served from the cache and yields the same result
+    assert_eq!(index.scan().unwrap(), chars);
+}
+
+#[test]
+fn read_header_matches_full_read() {
+    const PATH: &str = "./tests/save/_TestMain121/player.gdc";
+
+    let mut current_char = char::Char::new();
+    current_char.read(&Path::new(PATH).to_path_buf()).unwrap();
+
+    let header = char::Char::read_header(Path::new(PATH)).unwrap();
+    assert_eq!(header, current_char.header);
+}
+
+#[test]
+fn rescan_picks_up_changes() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path().join("_Test");
+    fs::create_dir(&dir).unwrap();
+    fs::copy("./tests/save/_TestMain/player.gdc", dir.join("player.gdc")).unwrap();
+
+    let mut index = SaveIndex::new(root.path());
+    let chars = index.scan().unwrap();
+    assert_eq!(chars.len(), 1);
+    assert_eq!(chars[0].header.name, "TestMain");
+
+    fs::copy(
+        "./tests/save/_TestMain121/player.gdc",
+        dir.join("player.gdc"),
+    )
+    .unwrap();
+    let chars = index.scan().unwrap();
+    assert_eq!(chars[0].header.name, "TestOneTwoOne");
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(index.scan().unwrap().is_empty());
+}
+
+#[test]
+fn mod_folders() {
+    let save = tempfile::tempdir().unwrap();
+    for (root, dir, from) in [
+        ("main", "_Main", "_TestMain"),
+        ("user/gdx2", "_Modded", "_TestMain121"),
+    ] {
+        let dir = save.path().join(root).join(dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy(
+            Path::new("./tests/save").join(from).join("player.gdc"),
+            dir.join("player.gdc"),
+        )
+        .unwrap();
+    }
+
+    let main = save.path().join("main");
+    assert_eq!(SaveIndex::new(&main).scan().unwrap().len(), 1);
+
+    let chars = SaveIndex::new(&main).with_mods(true).scan().unwrap();
+    assert_eq!(chars.len(), 2);
+    assert_eq!(chars[0].mod_name, None);
+    assert_eq!(chars[1].mod_name.as_deref(), Some("gdx2"));
+    assert!(chars[1].label().ends_with(", mod gdx2)"));
+
+    // a mod folder tags its characters even when it's the root
+    let chars = SaveIndex::new(&save.path().join("user/gdx2"))
+        .scan()
+        .unwrap();
+    assert_eq!(chars[0].mod_name.as_deref(), Some("gdx2"));
+}
+
+#[test]
+fn broken_saves_are_listed() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let root = tempfile::tempdir().unwrap();
+    let good = root.path().join(std::ffi::OsStr::from_bytes(b"_Caf\xe9"));
+    fs::create_dir(&good).unwrap();
+    fs::copy("./tests/save/_TestMain/player.gdc", good.join("player.gdc")).unwrap();
+
+    let garbage = root.path().join("_Garbage");
+    fs::create_dir(&garbage).unwrap();
+    fs::write(garbage.join("player.gdc"), b"not a save at all").unwrap();
+
+    let truncated = root.path().join("_Truncated");
+    fs::create_dir(&truncated).unwrap();
+    let data = fs::read("./tests/save/_TestMain/player.gdc").unwrap();
+    fs::write(truncated.join("player.gdc"), &data[..20]).unwrap();
+
+    let chars = SaveIndex::new(root.path()).scan().unwrap();
+    assert_eq!(chars.len(), 3);
+    assert!(chars[0].is_ok());
+    assert_eq!(chars[0].header.name, "TestMain");
+    assert_eq!(chars[1].dir, garbage);
+    assert!(matches!(chars[1].health, Health::Corrupt { .. }));
+    assert!(chars[1].label().starts_with("_Garbage (corrupt at offset "));
+    assert!(matches!(chars[2].health, Health::Corrupt { .. }));
+}
+
+#[test]
+fn health_check_reads_past_the_header() {
+    let root = tempfile::tempdir().unwrap();
+    let save = |dir: &str, data: &[u8]| {
+        let dir = root.path().join(dir);
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("player.gdc"), data).unwrap();
+    };
+
+    // a string length in the inventory, past the header
+    let mut data = fs::read("./tests/save/_TestMain/player.gdc").unwrap();
+    let item = data
+        .windows(13)
+        .position(|w| w == b"records/items")
+        .unwrap();
+    data[item - 4..item].copy_from_slice(&0x00ff_ffff_u32.to_le_bytes());
+    save("_Body", &data);
+
+    // blocks of an unknown version open like they do in the editor
+    save("_Newer", &bumped_stats().0);
+
+    // the header alone looks fine
+    let mut index = SaveIndex::new(root.path());
+    assert!(index.scan().unwrap().iter().all(|c| c.is_ok()));
+
+    let chars = index.with_health_check(true).scan().unwrap();
+    assert_eq!(chars.len(), 2);
+    assert!(chars[0].is_ok());
+    assert_eq!(chars[0].header.name, "TestMain");
+    assert!(chars[1].dir.ends_with("_Body"));
+    assert_eq!(
+        chars[1].health,
+        Health::Corrupt {
+            offset: Some(item as u64 - 4),
+            reason: "string length 16777215 exceeds the limit 4096".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn health_of_errors() {
+    let e = Error::new(
+        ErrorKind::UnsupportedVersion {
+            actual: 3,
+            expected: vec![1, 2],
+        },
+        Location {
+            offset: Some(8),
+            ..Default::default()
+        },
+    );
+    assert_eq!(Health::from(&e), Health::Unsupported { version: 3 });
+    assert_eq!(Health::from(&e).to_string(), "unsupported version 3");
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::io::Cursor;
+use yagde::gd::char;
+use yagde::gd::error::ErrorKind;
+use yagde::gd::explain::explain;
+use yagde::gd::Limits;
+
+const PATH: &str = "./tests/save/_TestMain/player.gdc";
+
+/// Plain text TestMain with the int named `field` replaced by `v`.
+fn patched(field: &str, skip: usize, v: u32) -> Vec<u8> {
+    let mut data = fs::read(PATH).unwrap();
+    let f = explain(&data)
+        .fields
+        .into_iter()
+        .find(|f| f.name == field)
+        .unwrap();
+    let at = f.offset as usize + skip;
+    data[at..at + 4].copy_from_slice(&v.to_le_bytes());
+    data
+}
+
+#[test]
+fn string_limit() {
+    let data = fs::read(PATH).unwrap();
+
+    let mut c = char::Char::new();
+    let err = c
+        .with_limits(Limits {
+            string_len: 8,
+            ..Default::default()
+        })
+        .read_from(Cursor::new(&data))
+        .unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::LimitExceeded {
+            what: "string length",
+            actual: 21,
+            limit: 8
+        }
+    ));
+    assert_eq!(err.path(), "Header");
+}
+
+#[test]
+fn sack_limit() {
+    let data = patched("Inventory.sacks", 0, u32::MAX);
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::LimitExceeded { what: "sacks", .. }
+    ));
+}
+
+#[test]
+fn count_past_block_end() {
+    let data = patched("Inventory.sack[0].items", 0, 1000);
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::PastBlockEnd { what: "items", .. }
+    ));
+    assert_eq!(err.path(), "Inventory > sack 0");
+}
+
+#[test]
+fn count_of_large_elements() {
+    // fewer items than bytes left in the sack, but too many to fit
+    let data = patched("Inventory.sack[0].items", 0, 100);
+    let at = explain(&data)
+        .fields
+        .into_iter()
+        .find(|f| f.name == "Inventory.sack[0].items")
+        .unwrap()
+        .offset;
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    let ErrorKind::PastBlockEnd {
+        what: "items",
+        actual,
+        available,
+    } = err.kind
+    else {
+        panic!("unexpected error {err}");
+    };
+    assert!(available >= 100);
+    assert!(actual > available);
+    assert_eq!(err.offset(), Some(at));
+}
+
+#[test]
+fn block_past_enclosing_end() {
+    // length of the Bio block, right after its sequence number
+    let data = patched("Bio", 4, 0x0100_0000);
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::PastBlockEnd { what: "block", .. }
+    ));
+}
+
+#[test]
+fn corrupted_bytes_never_panic() {
+    let mut data = fs::read(PATH).unwrap();
+
+    for i in (0..data.len()).step_by(3) {
+        data[i] = !data[i];
+        let _ = char::Char::from_reader(Cursor::new(&data));
+        data[i] = !data[i];
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::io::Cursor;
+use yagde::gd::char::{self, Format};
+use yagde::gd::error::ErrorKind;
+
+const PATH: &str = "./tests/save/_TestMain/player.gdc";
+
+fn write(c: &char::Char) -> Vec<u8> {
+    let mut data = Vec::new();
+    c.to_writer(Cursor::new(&mut data)).unwrap();
+    data
+}
+
+#[test]
+fn upgrade_and_back() {
+    let data = fs::read(PATH).unwrap();
+    let original = char::Char::from_reader(Cursor::new(&data)).unwrap();
+    let old = original.format();
+    assert_ne!(old, Format::LATEST);
+
+    let mut c = original.clone();
+    assert!(c.migrate(&Format::LATEST).unwrap().is_empty());
+    let mut upgraded = char::Char::from_reader(Cursor::new(write(&c))).unwrap();
+    assert_eq!(upgraded.format(), Format::LATEST);
+
+    assert!(upgraded.migrate(&old).unwrap().is_empty());
+    assert_eq!(write(&upgraded), data);
+}
+
+#[test]
+fn downgrade() {
+    let data = fs::read(PATH).unwrap();
+    let mut c = char::Char::from_reader(Cursor::new(&data)).unwrap();
+
+    let oldest = Format {
+        header: 1,
+        char: 6,
+        info: 3,
+        inventory: 4,
+        stash: 5,
+        skills: 5,
+        ui: 4,
+        stats: 7,
+    };
+    let lost = c.migrate(&oldest).unwrap();
+    assert_eq!(
+        lost,
+        [
+            "Header.expansion_status",
+            "Info.loot_filters",
+            "UI.skill_sets",
+            "Stats.difficulty_skip"
+        ]
+    );
+
+    let read = char::Char::from_reader(Cursor::new(write(&c))).unwrap();
+    assert_eq!(read.format(), oldest);
+    assert_eq!(read, c);
+}
+
+#[test]
+fn unsupported_target() {
+    let mut c = char::Char::from_reader(Cursor::new(fs::read(PATH).unwrap())).unwrap();
+    let before = c.clone();
+
+    let err = c
+        .migrate(&Format {
+            stats: 8,
+            ..Format::LATEST
+        })
+        .unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ErrorKind::UnsupportedVersion { actual: 8, .. }
+    ));
+    assert_eq!(err.block_seq(), Some(16));
+    assert_eq!(c, before);
+}
@@ -1,16 +1,17 @@
 use yagde::gd::char;
 use std::path::Path;
+use tempfile::TempDir;
 
 #[test]
 fn reset_deaths() {
     const PATH: &str = "./tests/save/_TestMain/player.gdc";
-    const NEW_PATH: &str = "./tests/save/_TestMain/test_reset_deaths.gdc";
+    let tmp = TempDir::new().unwrap();
+    let new_path = &tmp.path().join("test_reset_deaths.gdc");
 
     let mut current_char = char::Char::new();
     current_char.read(&Path::new(&PATH).to_path_buf()).unwrap();
 
     let mut new_char = current_char.clone();
-    let new_path = &Path::new(&NEW_PATH).to_path_buf();
 
     new_char.reset_deaths().save_as(new_path).unwrap();
     new_char.read(new_path).unwrap();
@@ -21,13 +22,13 @@ fn reset_deaths() {
 #[test]
 fn reset_attributes() {
     const PATH: &str = "./tests/save/_TestMain/player.gdc";
-    const NEW_PATH: &str = "./tests/save/_TestMain/test_reset_attributes.gdc";
+    let tmp = TempDir::new().unwrap();
+    let new_path = &tmp.path().join("test_reset_attributes.gdc");
 
     let mut current_char = char::Char::new();
     current_char.read(&Path::new(&PATH).to_path_buf()).unwrap();
 
     let mut new_char = current_char.clone();
-    let new_path = &Path::new(&NEW_PATH).to_path_buf();
 
     new_char.reset_attributes().save_as(new_path).unwrap();
 
@@ -44,16 +45,16 @@ fn reset_attributes() {
 #[test]
 fn reset_devotions() {
     const PATH: &str = "./tests/save/_TestMain/player.gdc";
-    const NEW_PATH: &str = "./tests/save/_TestMain/test_reset_devotions.gdc";
+    let tmp = TempDir::new().unwrap();
+    let new_path = &tmp.path().join("test_reset_devotions.gdc");
 
     let mut current_char = char::Char::new();
     current_char.read(&Path::new(&PATH).to_path_buf()).unwrap();
 
     let mut new_char = current_char.clone();
-    let new_path = &Path::new(&NEW_PATH).to_path_buf();
     new_char.reset_devotions().save_as(new_path).unwrap();
 
-    new_char.read(&Path::new(&NEW_PATH).to_path_buf()).unwrap();
+    new_char.read(new_path).unwrap();
 
     assert_eq!(new_char.bio.devotion_points, new_char.bio.total_devotion);
 }
@@ -61,16 +62,16 @@ fn reset_devotions() {
 #[test]
 fn reset_skills() {
     const PATH: &str = "./tests/save/_TestMain/player.gdc";
-    const NEW_PATH: &str = "./tests/save/_TestMain/test_reset_skills.gdc";
+    let tmp = TempDir::new().unwrap();
+    let new_path = &tmp.path().join("test_reset_skills.gdc");
 
     let mut current_char = char::Char::new();
     current_char.read(&Path::new(&PATH).to_path_buf()).unwrap();
 
     let mut new_char = current_char.clone();
-    let new_path = &Path::new(&NEW_PATH).to_path_buf();
     new_char.reset_skills().save_as(new_path).unwrap();
 
-    new_char.read(&Path::new(&NEW_PATH).to_path_buf()).unwrap();
+    new_char.read(new_path).unwrap();
 
     /*
        3 Skill Points per level from Levels 2 to 50 (147 Skill Points)
@@ -0,0 +1,26 @@
+use std::fs;
+use yagde::gd::char;
+
+#[test]
+fn roundtrip_all_fixtures() {
+    const SAVE_DIR: &str = "./tests/save";
+
+    let mut failures = Vec::new();
+    let mut checked = 0;
+
+    for entry in fs::read_dir(SAVE_DIR).unwrap() {
+        let path = entry.unwrap().path().join("player.gdc");
+        if !path.exists() {
+            continue;
+        }
+
+        let data = fs::read(&path).unwrap();
+        if let Err(e) = char::Char::verify_roundtrip(&data) {
+            failures.push(format!("{}: {e}", path.display()));
+        }
+        checked += 1;
+    }
+
+    assert!(checked > 0);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
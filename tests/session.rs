@@ -0,0 +1,87 @@
+use std::fs;
+use tempfile::TempDir;
+use yagde::gd::char;
+use yagde::gd::diff::Change;
+use yagde::session::Session;
+
+fn session() -> (TempDir, Session) {
+    let tmp = TempDir::new().unwrap();
+    let file = tmp.path().join("player.gdc");
+    fs::copy("./tests/save/_TestMain/player.gdc", &file).unwrap();
+    let session = Session::open(&file).unwrap();
+    (tmp, session)
+}
+
+#[test]
+fn undo_redo() {
+    let (_tmp, mut s) = session();
+    let original = s.char().clone();
+    assert!(!s.can_undo());
+
+    s.edit(|c| {
+        c.add_money(10);
+    })
+    .unwrap()
+    .unwrap();
+    let changes = s
+        .edit(|c| {
+            c.rename("Other");
+        })
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        changes[0],
+        Change::Value {
+            field: "Header.name",
+            ..
+        }
+    ));
+    assert!(s
+        .edit(|c| {
+            c.reset_deaths();
+        })
+        .unwrap()
+        .is_none());
+    assert!(s.is_dirty());
+    assert_eq!(s.pending().len(), 2);
+
+    assert!(s.undo());
+    assert_eq!(s.char().header.name, "TestMain");
+    assert!(s.undo());
+    assert!(!s.undo());
+    assert_eq!(*s.char(), original);
+    assert!(!s.is_dirty());
+
+    assert!(s.redo());
+    assert_eq!(s.char().info.money, original.info.money + 10);
+    s.edit(|c| {
+        c.reset_skills();
+    })
+    .unwrap()
+    .unwrap();
+    assert!(!s.can_redo());
+}
+
+#[test]
+fn discard_and_save() {
+    let (_tmp, mut s) = session();
+    assert!(!s.discard());
+
+    s.edit(|c| {
+        c.add_money(10);
+    })
+    .unwrap();
+    assert!(s.discard());
+    assert!(!s.is_dirty());
+    assert!(s.undo());
+    assert!(s.is_dirty());
+
+    let before = fs::read(s.file()).unwrap();
+    s.save().unwrap();
+    assert!(!s.is_dirty());
+    assert_ne!(fs::read(s.file()).unwrap(), before);
+
+    let mut read = char::Char::new();
+    read.read(&s.file().to_path_buf()).unwrap();
+    assert_eq!(read.info.money, s.char().info.money);
+}
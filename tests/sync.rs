@@ -0,0 +1,176 @@
+use copy_dir::copy_dir;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use yagde::gd::char;
+use yagde::sync::{CloudSync, Side, State};
+
+/// A local and a cloud folder, both with `_TestMain`, and `_TestMain121`
+/// only in the local one.
+fn folders() -> (TempDir, PathBuf, PathBuf) {
+    let tmp = TempDir::new().unwrap();
+    let local = tmp.path().join("local");
+    let cloud = tmp.path().join("cloud");
+    fs::create_dir_all(&local).unwrap();
+    fs::create_dir_all(&cloud).unwrap();
+    for name in ["_TestMain", "_TestMain121"] {
+        copy_dir(Path::new("./tests/save").join(name), local.join(name)).unwrap();
+    }
+    copy_dir("./tests/save/_TestMain", cloud.join("_Renamed")).unwrap();
+
+    // the fixtures share the same uid
+    let file = local.join("_TestMain121/player.gdc");
+    let mut c = char::Char::new();
+    c.read(&file).unwrap();
+    *c.uid = [1; 16];
+    c.save_as(&file).unwrap();
+    (tmp, local, cloud)
+}
+
+fn play(file: &Path, seconds: u32) {
+    let mut c = char::Char::new();
+    c.read(&file.to_path_buf()).unwrap();
+    c.stats.playtime += seconds;
+    c.save_as(&file.to_path_buf()).unwrap();
+}
+
+#[test]
+fn pairs_by_uid() {
+    let (_tmp, local, cloud) = folders();
+    let sync = CloudSync::new(&local, &cloud);
+
+    let pairs = sync.pairs().unwrap();
+    assert_eq!(pairs.len(), 2);
+    assert_eq!(pairs[0].name(), "TestMain");
+    assert!(pairs[0].local.is_some() && pairs[0].cloud.is_some());
+    assert_eq!(pairs[0].state, State::InSync);
+    assert_eq!(pairs[1].name(), "TestOneTwoOne");
+    assert_eq!(pairs[1].cloud, None);
+    assert_eq!(pairs[1].state, State::Newer(Side::Local));
+
+    play(&cloud.join("_Renamed/player.gdc"), 60);
+    let pairs = sync.pairs().unwrap();
+    assert_eq!(pairs[0].state, State::Newer(Side::Cloud));
+}
+
+#[test]
+fn apply_copies_the_newer() {
+    let (_tmp, local, cloud) = folders();
+    play(&local.join("_TestMain/player.gdc"), 60);
+    let sync = CloudSync::new(&local, &cloud);
+
+    let replaced = RefCell::new(Vec::new());
+    for pair in sync.pairs().unwrap() {
+        let State::Newer(side) = pair.state else {
+            panic!("{} is {}", pair.name(), pair.state);
+        };
+        sync.apply(&pair, side, |file| {
+            replaced.borrow_mut().push(file.to_path_buf());
+            Ok::<_, std::io::Error>(())
+        })
+        .unwrap();
+    }
+    assert_eq!(
+        replaced.into_inner(),
+        [
+            cloud.join("_Renamed/player.gdc"),
+            cloud.join("_TestMain121/player.gdc")
+        ]
+    );
+    assert_eq!(
+        fs::read(local.join("_TestMain/player.gdc")).unwrap(),
+        fs::read(cloud.join("_Renamed/player.gdc")).unwrap()
+    );
+    assert!(sync
+        .pairs()
+        .unwrap()
+        .iter()
+        .all(|p| p.state == State::InSync));
+}
+
+/// An error of the caller, which `apply` hands back unchanged.
+#[derive(Debug)]
+enum Refused {
+    Protected,
+    Io,
+}
+
+impl From<std::io::Error> for Refused {
+    fn from(_: std::io::Error) -> Self {
+        Refused::Io
+    }
+}
+
+#[test]
+fn failed_before_leaves_it_alone() {
+    let (_tmp, local, cloud) = folders();
+    let sync = CloudSync::new(&local, &cloud);
+    let pair = sync.pairs().unwrap().remove(1);
+
+    let err = sync.apply(&pair, Side::Local, |_| Err(Refused::Protected));
+    assert!(matches!(err, Err(Refused::Protected)));
+    assert!(!cloud.join("_TestMain121").exists());
+}
+
+#[test]
+fn equally_new_copies_conflict() {
+    let (_tmp, local, cloud) = folders();
+    let file = cloud.join("_Renamed/player.gdc");
+    let mut c = char::Char::new();
+    c.read(&file).unwrap();
+    c.add_money(1);
+    c.save_as(&file).unwrap();
+    let modified = fs::metadata(local.join("_TestMain/player.gdc"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    let sync = CloudSync::new(&local, &cloud);
+    let pair = sync.pairs().unwrap().remove(0);
+    assert_eq!(pair.state, State::Conflict);
+
+    // the caller picks the copy to keep
+    sync.apply(&pair, Side::Cloud, |_| Ok::<_, std::io::Error>(()))
+        .unwrap();
+    assert_eq!(
+        fs::read(local.join("_TestMain/player.gdc")).unwrap(),
+        fs::read(&file).unwrap()
+    );
+}
+
+#[test]
+fn interrupted_sync_is_recovered() {
+    let (tmp, local, cloud) = folders();
+    // moved aside, with the copy meant to replace it half made
+    let staging = tmp.path().join(".yagde_staging/local");
+    fs::create_dir_all(staging.join("old")).unwrap();
+    fs::create_dir_all(staging.join("new/_TestMain121")).unwrap();
+    fs::rename(local.join("_TestMain121"), staging.join("old/_TestMain121")).unwrap();
+
+    let sync = CloudSync::new(&local, &cloud);
+    let pairs = sync.pairs().unwrap();
+    assert_eq!(pairs.len(), 2);
+    assert!(local.join("_TestMain121/player.gdc").exists());
+    assert!(!tmp.path().join(".yagde_staging").exists());
+
+    // a sync stages next to the roots and leaves nothing behind
+    sync.apply(&pairs[1], Side::Local, |_| Ok::<_, std::io::Error>(()))
+        .unwrap();
+    let names = |dir: &Path| -> Vec<_> {
+        let mut names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(names(&cloud), ["_Renamed", "_TestMain121"]);
+    assert_eq!(names(tmp.path()), ["cloud", "local"]);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::bumped_stats;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use yagde::gd::char;
+use yagde::gd::error::ErrorKind;
+use yagde::gd::explain::explain;
+use yagde::session::Session;
+
+fn read_tolerant(data: &[u8]) -> char::Char {
+    let mut c = char::Char::new();
+    c.with_tolerant(true).read_from(Cursor::new(data)).unwrap();
+    c
+}
+
+#[test]
+fn strict_read_fails() {
+    let (data, _) = bumped_stats();
+
+    let err = char::Char::from_reader(Cursor::new(&data)).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("Unsupported version: 12"),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn tolerant_roundtrip() {
+    let (data, _) = bumped_stats();
+
+    let current_char = read_tolerant(&data);
+    let opaque = current_char.opaque_blocks();
+    assert_eq!(opaque.len(), 1);
+    assert_eq!(opaque[0].seq, 16);
+    assert_eq!(opaque[0].version, 12);
+
+    let mut out = Vec::new();
+    current_char.to_writer(&mut out).unwrap();
+    assert_eq!(out, data);
+}
+
+#[test]
+fn tolerant_edit_known_blocks() {
+    let (data, _) = bumped_stats();
+
+    let mut new_char = read_tolerant(&data);
+    new_char.info.money += 1000;
+    new_char.rename("TestTolerant");
+
+    let mut out = Vec::new();
+    new_char.to_writer(&mut out).unwrap();
+
+    let read_char = read_tolerant(&out);
+    assert_eq!(read_char.header.name, "TestTolerant");
+    assert_eq!(read_char.info.money, new_char.info.money);
+    assert_eq!(read_char.opaque_blocks(), new_char.opaque_blocks());
+
+    // the skipped block made it to the new file byte for byte
+    let (_, start) = bumped_stats();
+    let block = &data[start..start + new_char.opaque_blocks()[0].len()];
+    assert!(out.windows(block.len()).any(|w| w == block));
+}
+
+#[test]
+fn tolerant_reencrypt_fails() {
+    let (data, _) = bumped_stats();
+
+    let mut new_char = read_tolerant(&data);
+    let err = new_char
+        .with_seed(0x1234_5678)
+        .to_writer(&mut Vec::new())
+        .unwrap_err();
+    assert!(err.to_string().contains("Opaque block 16"), "{err}");
+}
+
+/// The encrypted `_TestVanillaFemaleHC` with its Stats block version bumped
+/// to 12. The version is XORed with the key, so flipping the same bits of
+/// the raw bytes bumps it without knowing the key.
+fn bumped_encrypted_stats() -> Vec<u8> {
+    let mut data = fs::read("./tests/save/_TestVanillaFemaleHC/player.gdc").unwrap();
+    let version = explain(&data)
+        .fields
+        .into_iter()
+        .find(|f| f.name == "Stats.version")
+        .unwrap();
+    assert_eq!(version.value.to_string(), "version 11");
+    let at = version.offset as usize;
+    for (b, x) in data[at..at + 4].iter_mut().zip((11_u32 ^ 12).to_le_bytes()) {
+        *b ^= x;
+    }
+    data
+}
+
+#[test]
+fn encrypted_edits_are_refused() {
+    let data = bumped_encrypted_stats();
+    let c = read_tolerant(&data);
+    assert_ne!(c.seed(), 0);
+    assert!(c.is_read_only());
+    assert!(!read_tolerant(&bumped_stats().0).is_read_only());
+
+    // unchanged, it writes back as it was read
+    c.check_writable().unwrap();
+    let mut out = Vec::new();
+    c.to_writer(&mut out).unwrap();
+    assert_eq!(out, data);
+
+    let mut session = Session::new(Path::new("player.gdc"), c);
+    let err = session
+        .edit(|c| {
+            c.add_money(10);
+        })
+        .unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::OpaqueBlockKeyMismatch(16)));
+    assert!(!session.is_dirty());
+}
@@ -0,0 +1,129 @@
+#![cfg(feature = "tui")]
+
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Terminal;
+use std::fs;
+use tempfile::TempDir;
+use yagde::session::Session;
+use yagde::tui::{App, Pane, Request};
+
+fn app() -> (TempDir, App) {
+    let tmp = TempDir::new().unwrap();
+    let file = tmp.path().join("player.gdc");
+    fs::copy("./tests/save/_TestMain/player.gdc", &file).unwrap();
+    let app = App::new(Session::open(&file).unwrap());
+    (tmp, app)
+}
+
+fn press(app: &mut App, keys: &[KeyCode]) -> Option<Request> {
+    keys.iter()
+        .map(|k| app.handle_key(KeyEvent::from(*k)))
+        .last()
+        .flatten()
+}
+
+fn type_text(app: &mut App, text: &str) {
+    for c in text.chars() {
+        app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+}
+
+fn screen(app: &App) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal.draw(|f| app.draw(f)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let mut text = String::new();
+    for y in 0..buffer.area.height {
+        for x in 0..buffer.area.width {
+            text.push_str(buffer[(x, y)].symbol());
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[test]
+fn browse_panes() {
+    let (_tmp, mut app) = app();
+    let s = screen(&app);
+    assert!(s.contains("TestMain"));
+    assert!(s.contains("Iron"));
+    assert!(s.contains("94717"));
+
+    press(&mut app, &[KeyCode::Tab]);
+    assert_eq!(app.pane(), Pane::Skills);
+    assert!(screen(&app).contains("playerclass08/summon_skeleton1"));
+
+    press(&mut app, &[KeyCode::Char('5')]);
+    let s = screen(&app);
+    assert!(s.contains("Bag 0"));
+
+    press(&mut app, &[KeyCode::BackTab, KeyCode::BackTab]);
+    assert_eq!(app.pane(), Pane::Devotions);
+    press(&mut app, &[KeyCode::Char('6')]);
+    assert!(screen(&app).contains("kills"));
+}
+
+#[test]
+fn edit_undo_and_save() {
+    let (_tmp, mut app) = app();
+
+    // iron is the 7th row of the character pane
+    press(&mut app, &[KeyCode::Down; 6]);
+    press(&mut app, &[KeyCode::Enter]);
+    for _ in 0.."94717".len() {
+        press(&mut app, &[KeyCode::Backspace]);
+    }
+    type_text(&mut app, "12x");
+    press(&mut app, &[KeyCode::Enter]);
+    assert_eq!(app.status(), "\"12x\" is not a whole number");
+    assert!(!app.session().is_dirty());
+
+    // the input starts with the current value
+    press(&mut app, &[KeyCode::Enter]);
+    type_text(&mut app, "00");
+    press(&mut app, &[KeyCode::Enter]);
+    assert_eq!(app.session().char().info.money, 9471700);
+    assert!(screen(&app).contains("[modified]"));
+
+    press(&mut app, &[KeyCode::Char('u')]);
+    assert_eq!(app.session().char().info.money, 94717);
+    press(&mut app, &[KeyCode::Char('r')]);
+
+    // quitting with unsaved changes needs a second press
+    press(&mut app, &[KeyCode::Char('q')]);
+    assert!(!app.should_quit());
+    assert_eq!(press(&mut app, &[KeyCode::Char('s')]), Some(Request::Save));
+    app.save(|_| Ok(()));
+    assert_eq!(app.status(), "Saved");
+    assert!(!app.session().is_dirty());
+
+    let mut read = Session::open(app.session().file()).unwrap();
+    assert!(read
+        .edit(|c| {
+            c.add_money(0);
+        })
+        .unwrap()
+        .is_none());
+    assert_eq!(read.char().info.money, 9471700);
+
+    press(&mut app, &[KeyCode::Char('q')]);
+    assert!(app.should_quit());
+}
+
+#[test]
+fn skill_level() {
+    let (_tmp, mut app) = app();
+    press(
+        &mut app,
+        &[KeyCode::Char('2'), KeyCode::Enter, KeyCode::Backspace],
+    );
+    type_text(&mut app, "12");
+    press(&mut app, &[KeyCode::Enter]);
+    assert_eq!(
+        app.status(),
+        "skill records/skills/default/defaultkickattack.dbr: level 1 -> 12"
+    );
+    assert_eq!(app.session().char().skills.skills[0].level, 12);
+}
@@ -0,0 +1,315 @@
+//! `#[derive(GdBlock)]` for the save structures of yagde.
+//!
+//! Fields are read and written in declaration order, so a single struct
+//! definition describes the on-disk layout for both directions.
+//!
+//! Struct attributes:
+//! - `#[gd(block = 2, versions = [8])]` wraps the fields in block `2`, reads
+//!   the block version into the `version` field and checks it against
+//!   `versions`. Also implements `GdBlock`.
+//! - `#[gd(block = 0)]` without `versions` is a nested block that has no
+//!   version of its own, like inventory sacks.
+//! - `#[gd(name = "skill")]` is the element name used in error paths and
+//!   traces, e.g. `skill 3`. Defaults to the struct name.
+//!
+//! Field attributes:
+//! - `#[gd(since = 9)]`, `#[gd(until = 4)]` only read and write the field
+//!   when the block version is in range, reading resets it otherwise. `GdBlock::migrate` resets them to
+//!   their default when converting to a version out of that range.
+//! - `#[gd(as = u8)]` stores the field as another type, converting with
+//!   `From` both ways.
+//! - `#[gd(flatten)]` reads a nested structure without adding its field name
+//!   to the path.
+//! - `#[gd(skip)]` leaves the field out of the file.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, punctuated::Punctuated, spanned::Spanned, Data, DeriveInput, Error, Fields,
+    LitInt, LitStr, Token, Type,
+};
+
+#[proc_macro_derive(GdBlock, attributes(gd))]
+pub fn derive_gd_block(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct StructAttrs {
+    block: Option<LitInt>,
+    versions: Vec<LitInt>,
+    name: Option<LitStr>,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    since: Option<LitInt>,
+    until: Option<LitInt>,
+    as_type: Option<Type>,
+    flatten: bool,
+    skip: bool,
+}
+
+fn struct_attrs(input: &DeriveInput) -> syn::Result<StructAttrs> {
+    let mut a = StructAttrs::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("gd")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("block") {
+                a.block = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("versions") {
+                let value = meta.value()?;
+                let content;
+                syn::bracketed!(content in value);
+                a.versions = Punctuated::<LitInt, Token![,]>::parse_terminated(&content)?
+                    .into_iter()
+                    .collect();
+            } else if meta.path.is_ident("name") {
+                a.name = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("expected `block`, `versions` or `name`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if a.block.is_none() && !a.versions.is_empty() {
+        return Err(Error::new(input.ident.span(), "`versions` needs a `block`"));
+    }
+    Ok(a)
+}
+
+fn field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut a = FieldAttrs::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("gd")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("since") {
+                a.since = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("until") {
+                a.until = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("as") {
+                a.as_type = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("flatten") {
+                a.flatten = true;
+            } else if meta.path.is_ident("skip") {
+                a.skip = true;
+            } else {
+                return Err(meta.error("expected `since`, `until`, `as`, `flatten` or `skip`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(a)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let attrs = struct_attrs(&input)?;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(input.span(), "GdBlock only supports structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(
+            input.span(),
+            "GdBlock only supports structs with named fields",
+        ));
+    };
+
+    let versioned = !attrs.versions.is_empty();
+
+    let gd = quote! { crate::gd::gd_file };
+    let result = quote! { crate::gd::error::Result<()> };
+
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    let mut migrates = Vec::new();
+    // fields that depend on the version may be missing, so only the others
+    // count towards the size
+    let mut sizes = Vec::new();
+    for field in fields.named.iter() {
+        let f = field.ident.as_ref().expect("named field");
+        // the block version is handled together with the block itself
+        if versioned && f == "version" {
+            continue;
+        }
+
+        let a = field_attrs(field)?;
+        if a.skip {
+            continue;
+        }
+        let name = LitStr::new(&f.to_string(), f.span());
+
+        let (mut read, mut write) = match (&a.as_type, a.flatten) {
+            (Some(t), _) => (
+                quote! {
+                    let mut v = <#t as ::core::default::Default>::default();
+                    ReadWrite::read_named(&mut v, #name, f)?;
+                    self.#f = v.into();
+                },
+                quote! { ReadWrite::write(&<#t>::from(self.#f), f)?; },
+            ),
+            (None, true) => (
+                quote! { ReadWrite::read(&mut self.#f, f)?; },
+                quote! { ReadWrite::write(&self.#f, f)?; },
+            ),
+            (None, false) => (
+                quote! { ReadWrite::read_named(&mut self.#f, #name, f)?; },
+                quote! { ReadWrite::write(&self.#f, f)?; },
+            ),
+        };
+
+        if (a.since.is_some() || a.until.is_some()) && !versioned {
+            return Err(Error::new(
+                field.span(),
+                "`since` and `until` need a versioned block",
+            ));
+        }
+        if a.since.is_none() && a.until.is_none() {
+            let t = a.as_type.as_ref().unwrap_or(&field.ty);
+            sizes.push(quote! { <#t as #gd::ReadWrite>::MIN_SIZE });
+        }
+        if let Some(cond) = version_cond(&a, quote! { self.version }) {
+            // the struct may still hold a value from an earlier read
+            read = quote! {
+                if #cond {
+                    #read
+                } else {
+                    self.#f = ::core::default::Default::default();
+                }
+            };
+            write = quote! { if #cond { #write } };
+        }
+        if let Some(cond) = version_cond(&a, quote! { to }) {
+            migrates.push(quote! {
+                if !(#cond) {
+                    #gd::drop_field(&mut self.#f, #name, &mut lost);
+                }
+            });
+        }
+
+        reads.push(read);
+        writes.push(write);
+    }
+
+    let elem_name = attrs
+        .name
+        .clone()
+        .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+
+    let (generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // a block adds its sequence number, its length and its end marker, a
+    // versioned one its version as well
+    let overhead: usize = match (&attrs.block, versioned) {
+        (None, _) => 0,
+        (Some(_), false) => 12,
+        (Some(_), true) => 16,
+    };
+    let min_size = quote! { #overhead #(+ #sizes)* };
+
+    let Some(seq) = &attrs.block else {
+        return Ok(quote! {
+            impl #generics #gd::ReadWrite for #ident #ty_generics #where_clause {
+                const NAME: &'static str = #elem_name;
+                const MIN_SIZE: usize = #min_size;
+
+                fn read(&mut self, f: &mut impl #gd::GDReader) -> #result {
+                    use #gd::ReadWrite;
+                    #(#reads)*
+                    Ok(())
+                }
+
+                fn write(&self, f: &mut impl #gd::GDWriter) -> #result {
+                    use #gd::ReadWrite;
+                    #(#writes)*
+                    Ok(())
+                }
+            }
+        });
+    };
+
+    if !versioned {
+        return Ok(quote! {
+            impl #generics #gd::ReadWrite for #ident #ty_generics #where_clause {
+                const NAME: &'static str = #elem_name;
+                const MIN_SIZE: usize = #min_size;
+
+                fn read(&mut self, f: &mut impl #gd::GDReader) -> #result {
+                    use #gd::ReadWrite;
+                    let mut b = #gd::Block::default();
+                    f.read_block_start(&mut b, #seq)?;
+                    #(#reads)*
+                    f.read_block_end(&mut b)
+                }
+
+                fn write(&self, f: &mut impl #gd::GDWriter) -> #result {
+                    use #gd::ReadWrite;
+                    let mut b = #gd::Block::default();
+                    f.write_block_start(&mut b, #seq)?;
+                    #(#writes)*
+                    f.write_block_end(&mut b)
+                }
+            }
+        });
+    }
+    let versions = &attrs.versions;
+
+    Ok(quote! {
+        impl #generics #gd::GdBlock for #ident #ty_generics #where_clause {
+            const SEQ: u32 = #seq;
+            const VERSIONS: &'static [u32] = &[#(#versions),*];
+
+            fn version(&self) -> u32 {
+                self.version
+            }
+
+            fn migrate(
+                &mut self,
+                to: u32,
+            ) -> ::core::result::Result<::std::vec::Vec<&'static str>, crate::gd::error::ErrorKind> {
+                Self::check_version(to)?;
+                let mut lost = ::std::vec::Vec::new();
+                #(#migrates)*
+                self.version = to;
+                Ok(lost)
+            }
+        }
+
+        impl #generics #gd::ReadWrite for #ident #ty_generics #where_clause {
+            const NAME: &'static str = #elem_name;
+            const MIN_SIZE: usize = #min_size;
+
+            fn read(&mut self, f: &mut impl #gd::GDReader) -> #result {
+                use #gd::{GdBlock, ReadWrite};
+                let mut b = #gd::Block::default();
+                f.read_block_start(&mut b, Self::SEQ)?;
+                self.version = f.read_version(Self::VERSIONS)?;
+                #(#reads)*
+                f.read_block_end(&mut b)
+            }
+
+            fn write(&self, f: &mut impl #gd::GDWriter) -> #result {
+                use #gd::{GdBlock, ReadWrite};
+                let mut b = #gd::Block::default();
+                f.write_block_start(&mut b, Self::SEQ)?;
+                f.write_int(self.version)?;
+                #(#writes)*
+                f.write_block_end(&mut b)
+            }
+        }
+    })
+}
+
+/// Whether a field with `since`/`until` exists in the version `version`.
+fn version_cond(a: &FieldAttrs, version: TokenStream2) -> Option<TokenStream2> {
+    match (&a.since, &a.until) {
+        (None, None) => None,
+        (Some(s), None) => Some(quote! { #version >= #s }),
+        (None, Some(u)) => Some(quote! { #version <= #u }),
+        (Some(s), Some(u)) => Some(quote! { (#s..=#u).contains(&#version) }),
+    }
+}